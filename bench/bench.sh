@@ -4,6 +4,6 @@ curl -L "http://localhost:3000/run" -H "Content-Type: application/json" -d "{
     \"source_code\": \"fn main() {\n\tprintln!(\\\"Hello World\\\");\n}\",
     \"timeout\": 5,
     \"sample_testcases\": [
-        \"123\"
+        [\"123\", \"Hello World\"]
     ]
 }"
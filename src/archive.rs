@@ -0,0 +1,362 @@
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+use tar::EntryType;
+use zip::ZipArchive;
+
+use crate::{check_paths, relative_path, types::CodingError, Files};
+
+// Unpacks zip and tar.gz archives of submitted projects. Entries are read
+// into memory and written out like any other submitted file, so nothing in
+// the archive can place a file outside the submission folder.
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+// End of central directory record, which ends zip archives along with a
+// comment of up to 64KiB
+const ZIP_END_MAGIC: &[u8] = b"PK\x05\x06";
+const ZIP_END_SIZE: usize = 22;
+
+fn invalid(reason: impl ToString) -> CodingError {
+    CodingError::InvalidSubmission {
+        reason: reason.to_string(),
+    }
+}
+
+pub fn extract(archive: &[u8], size_limit: u64, entry_limit: u64) -> Result<Files, CodingError> {
+    let mut extractor = Extractor {
+        files: Vec::new(),
+        entries: 0,
+        remaining: size_limit,
+        size_limit,
+        entry_limit,
+    };
+    if archive.starts_with(ZIP_MAGIC) {
+        extractor.zip(archive)?;
+    } else if archive.starts_with(GZIP_MAGIC) {
+        extractor.tar_gz(archive)?;
+    } else {
+        return Err(invalid("Archives have to be zip or tar.gz"));
+    }
+    if extractor.files.is_empty() {
+        return Err(invalid("The archive has no files"));
+    }
+    check_paths(&extractor.files)?;
+    Ok(extractor.files)
+}
+
+// Entries the zip's central directory lists. The zip crate keeps only one
+// of the entries with the same name, this tells whether there were more.
+fn zip_entry_count(archive: &[u8]) -> Option<u16> {
+    let search_start = archive
+        .len()
+        .saturating_sub(ZIP_END_SIZE + u16::MAX as usize);
+    let end = archive[search_start..]
+        .windows(ZIP_END_MAGIC.len())
+        .rposition(|window| window == ZIP_END_MAGIC)?
+        + search_start;
+    let count = archive.get(end + 10..end + 12)?;
+    Some(u16::from_le_bytes([count[0], count[1]]))
+}
+
+struct Extractor {
+    files: Files,
+    entries: u64,
+    // Bytes left before the extracted files go over the size limit
+    remaining: u64,
+    size_limit: u64,
+    entry_limit: u64,
+}
+
+impl Extractor {
+    fn count_entry(&mut self) -> Result<(), CodingError> {
+        self.entries += 1;
+        if self.entries > self.entry_limit {
+            return Err(invalid(format!(
+                "The archive has more than {} entries",
+                self.entry_limit
+            )));
+        }
+        Ok(())
+    }
+
+    // Reads an entry, trusting the actual size rather than the one the
+    // archive claims
+    fn add(&mut self, path: &str, content: impl Read) -> Result<(), CodingError> {
+        let path = relative_path(path)?;
+        let mut bytes = Vec::new();
+        content
+            .take(self.remaining + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|err| invalid(format!("Unable to read `{}` :: {}", path, err)))?;
+        if bytes.len() as u64 > self.remaining {
+            return Err(invalid(format!(
+                "The archive extracts to more than {} bytes",
+                self.size_limit
+            )));
+        }
+        self.remaining -= bytes.len() as u64;
+        self.files.push((path, bytes));
+        Ok(())
+    }
+
+    fn zip(&mut self, archive: &[u8]) -> Result<(), CodingError> {
+        let entry_count = zip_entry_count(archive);
+        let mut archive = ZipArchive::new(Cursor::new(archive))
+            .map_err(|err| invalid(format!("Unable to read the zip archive :: {}", err)))?;
+        // Zip64 archives put u16::MAX there and the count elsewhere
+        if entry_count.is_some_and(|count| count != u16::MAX && count as usize > archive.len()) {
+            return Err(invalid("The archive has the same path more than once"));
+        }
+        for index in 0..archive.len() {
+            self.count_entry()?;
+            let entry = archive
+                .by_index(index)
+                .map_err(|err| invalid(format!("Unable to read the zip archive :: {}", err)))?;
+            let name = entry
+                .name()
+                .map_err(|err| invalid(format!("Unable to read the zip archive :: {}", err)))?
+                .into_owned();
+            if entry.is_dir() {
+                continue;
+            }
+            if entry.is_symlink() {
+                return Err(invalid(format!("`{}` is a symbolic link", name)));
+            }
+            self.add(&name, entry)?;
+        }
+        Ok(())
+    }
+
+    fn tar_gz(&mut self, archive: &[u8]) -> Result<(), CodingError> {
+        let mut archive = tar::Archive::new(GzDecoder::new(archive));
+        let entries = archive
+            .entries()
+            .map_err(|err| invalid(format!("Unable to read the tar archive :: {}", err)))?;
+        for entry in entries {
+            self.count_entry()?;
+            let entry = entry
+                .map_err(|err| invalid(format!("Unable to read the tar archive :: {}", err)))?;
+            let name = match entry.path() {
+                Ok(path) => path.to_string_lossy().into_owned(),
+                Err(err) => {
+                    return Err(invalid(format!(
+                        "Unable to read the tar archive :: {}",
+                        err
+                    )))
+                }
+            };
+            match entry.header().entry_type() {
+                EntryType::Regular | EntryType::Continuous => self.add(&name, entry)?,
+                // `git archive` starts with a global header holding the commit
+                EntryType::Directory | EntryType::XGlobalHeader => {}
+                _ => return Err(invalid(format!("`{}` is not a regular file", name))),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    const SIZE_LIMIT: u64 = 1024;
+    const ENTRY_LIMIT: u64 = 8;
+
+    fn zip(build: impl FnOnce(&mut ZipWriter<Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        build(&mut writer);
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn zip_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+        zip(|writer| {
+            for (path, content) in files {
+                writer
+                    .start_file(*path, SimpleFileOptions::default())
+                    .unwrap();
+                writer.write_all(content).unwrap();
+            }
+        })
+    }
+
+    fn tar_gz(build: impl FnOnce(&mut tar::Builder<GzEncoder<Vec<u8>>>)) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        build(&mut builder);
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    // Written by hand, as the tar crate won't put `..` in a path
+    fn tar_file(builder: &mut tar::Builder<GzEncoder<Vec<u8>>>, path: &str, content: &[u8]) {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_entry_type(EntryType::Regular);
+        header.set_cksum();
+        builder.append(&header, content).unwrap();
+    }
+
+    fn reason(archive: &[u8]) -> String {
+        testing::reason(extract(archive, SIZE_LIMIT, ENTRY_LIMIT))
+    }
+
+    #[test]
+    fn extracts_zip() {
+        let archive = zip(|writer| {
+            writer
+                .add_directory("src/", SimpleFileOptions::default())
+                .unwrap();
+            writer
+                .start_file("src/main.rs", SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"fn main() {}").unwrap();
+            writer
+                .start_file("./Cargo.toml", SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"[package]").unwrap();
+        });
+        let files = extract(&archive, SIZE_LIMIT, ENTRY_LIMIT).unwrap();
+        assert_eq!(
+            files,
+            [
+                ("src/main.rs".to_string(), b"fn main() {}".to_vec()),
+                ("Cargo.toml".to_string(), b"[package]".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_tar_gz() {
+        let archive = tar_gz(|builder| {
+            tar_file(builder, "pkg/a.py", b"import b");
+            tar_file(builder, "pkg/b.py", b"");
+        });
+        let files = extract(&archive, SIZE_LIMIT, ENTRY_LIMIT).unwrap();
+        assert_eq!(
+            files,
+            [
+                ("pkg/a.py".to_string(), b"import b".to_vec()),
+                ("pkg/b.py".to_string(), Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert_eq!(reason(b"plain text"), "Archives have to be zip or tar.gz");
+        assert_eq!(reason(&zip(|_| {})), "Archives have to be zip or tar.gz");
+        assert_eq!(reason(&tar_gz(|_| {})), "The archive has no files");
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_submission() {
+        let archive = zip_files(&[("../evil.sh", b"")]);
+        assert_eq!(reason(&archive), "`../evil.sh` is not a valid file path");
+        let archive = zip_files(&[("/etc/cron.d/evil", b"")]);
+        assert_eq!(
+            reason(&archive),
+            "`/etc/cron.d/evil` is not a valid file path"
+        );
+        let archive = tar_gz(|builder| tar_file(builder, "a/../../evil.sh", b""));
+        assert_eq!(
+            reason(&archive),
+            "`a/../../evil.sh` is not a valid file path"
+        );
+    }
+
+    #[test]
+    fn rejects_symlinks() {
+        let archive = zip(|writer| {
+            writer
+                .add_symlink("passwd", "/etc/passwd", SimpleFileOptions::default())
+                .unwrap();
+        });
+        assert_eq!(reason(&archive), "`passwd` is a symbolic link");
+
+        let archive = tar_gz(|builder| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            builder
+                .append_link(&mut header, "passwd", "/etc/passwd")
+                .unwrap();
+        });
+        assert_eq!(reason(&archive), "`passwd` is not a regular file");
+    }
+
+    #[test]
+    fn rejects_duplicate_paths() {
+        let archive = tar_gz(|builder| {
+            tar_file(builder, "main.py", b"print(1)");
+            tar_file(builder, "./main.py", b"print(2)");
+        });
+        assert_eq!(reason(&archive), "`main.py` is submitted more than once");
+
+        let archive = zip_files(&[("main.py", b""), ("./main.py", b"")]);
+        assert_eq!(reason(&archive), "`main.py` is submitted more than once");
+
+        let archive = zip_files(&[("src", b""), ("src/main.rs", b"")]);
+        assert_eq!(
+            reason(&archive),
+            "`src/main.rs` can't be in `src`, which is a file"
+        );
+    }
+
+    #[test]
+    fn counts_zip_entries() {
+        let archive = zip_files(&[("a", b""), ("b", b""), ("c", b"")]);
+        assert_eq!(zip_entry_count(&archive), Some(3));
+        assert_eq!(zip_entry_count(b"PK\x03\x04"), None);
+    }
+
+    #[test]
+    fn enforces_the_size_limit() {
+        let content = vec![b'a'; SIZE_LIMIT as usize / 2 + 1];
+        let archive = zip_files(&[("a", &content)]);
+        assert!(extract(&archive, SIZE_LIMIT, ENTRY_LIMIT).is_ok());
+        // Compressed well below the limit, the files add up past it
+        let archive = zip_files(&[("a", &content), ("b", &content)]);
+        assert_eq!(
+            reason(&archive),
+            format!("The archive extracts to more than {} bytes", SIZE_LIMIT)
+        );
+        let archive = tar_gz(|builder| {
+            tar_file(builder, "a", &content);
+            tar_file(builder, "b", &content);
+        });
+        assert_eq!(
+            reason(&archive),
+            format!("The archive extracts to more than {} bytes", SIZE_LIMIT)
+        );
+    }
+
+    #[test]
+    fn enforces_the_entry_limit() {
+        let names: Vec<String> = (0..=ENTRY_LIMIT).map(|i| format!("{}.txt", i)).collect();
+        let archive = zip(|writer| {
+            for name in &names {
+                writer
+                    .start_file(name.as_str(), SimpleFileOptions::default())
+                    .unwrap();
+            }
+        });
+        let limit = format!("The archive has more than {} entries", ENTRY_LIMIT);
+        assert_eq!(reason(&archive), limit);
+        // Directories count too
+        let archive = tar_gz(|builder| {
+            for name in &names {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(EntryType::Directory);
+                header.set_size(0);
+                builder.append_data(&mut header, name, &[][..]).unwrap();
+            }
+        });
+        assert_eq!(reason(&archive), limit);
+    }
+}
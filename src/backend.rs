@@ -0,0 +1,244 @@
+use log::error;
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    config::{Config, SandboxKind},
+    types::Limits,
+};
+
+// External sandboxing tools. A backend takes the command the runner was
+// about to spawn and returns the one that runs it inside the tool instead,
+// with the submission folder mounted writable at /box (/submission in
+// isolate, whose own box is at /box) and the configured host paths
+// read-only. Extra arguments from `SANDBOX_ARGS` are passed to the tool as
+// is, so deployments can bring their own policies.
+
+pub struct Wrapped {
+    pub command: Command,
+    // Runs once the wrapped command has exited and been reaped
+    pub cleanup: Option<Box<dyn FnOnce() + Send>>,
+}
+
+pub trait SandboxBackend: Send + Sync {
+    fn wrap(&self, command: &Command, workdir: &Path, limits: &Limits) -> io::Result<Wrapped>;
+}
+
+pub fn from_config(config: &Config) -> Option<Box<dyn SandboxBackend>> {
+    let binary = |default: &str| {
+        config
+            .sandbox_binary
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(default))
+    };
+    match config.sandbox {
+        SandboxKind::None | SandboxKind::Namespaces => None,
+        SandboxKind::Bubblewrap => Some(Box::new(Bubblewrap {
+            binary: binary("bwrap"),
+            readonly_paths: config.sandbox_readonly_paths.clone(),
+            extra_args: config.sandbox_args.clone(),
+        })),
+        SandboxKind::Nsjail => Some(Box::new(Nsjail {
+            binary: binary("nsjail"),
+            readonly_paths: config.sandbox_readonly_paths.clone(),
+            extra_args: config.sandbox_args.clone(),
+        })),
+        SandboxKind::Isolate => Some(Box::new(Isolate {
+            binary: binary("isolate"),
+            readonly_paths: config.sandbox_readonly_paths.clone(),
+            extra_args: config.sandbox_args.clone(),
+        })),
+    }
+}
+
+// Not every tool searches PATH for the program, so bare names are resolved
+// here. Paths like `./main` are left alone and resolve inside the mounted
+// submission folder.
+fn resolve_program(program: &OsStr) -> OsString {
+    if Path::new(program).components().count() > 1 {
+        return program.to_os_string();
+    }
+    let paths = env::var_os("PATH").unwrap_or_default();
+    for dir in env::split_paths(&paths) {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return candidate.into_os_string();
+        }
+    }
+    program.to_os_string()
+}
+
+fn program_and_args(command: &Command) -> Vec<OsString> {
+    let mut argv = vec![resolve_program(command.get_program())];
+    argv.extend(command.get_args().map(OsStr::to_os_string));
+    argv
+}
+
+fn seconds(limits: &Limits) -> u64 {
+    // Tools only take whole seconds; our own timer stays the precise one
+    limits.time.as_secs() + 1
+}
+
+// bubblewrap has no resource limits of its own. The runner's rlimits are
+// inherited, but the process count is only limited with a cgroup.
+pub struct Bubblewrap {
+    binary: PathBuf,
+    readonly_paths: Vec<PathBuf>,
+    extra_args: Vec<String>,
+}
+
+impl SandboxBackend for Bubblewrap {
+    fn wrap(&self, command: &Command, workdir: &Path, _limits: &Limits) -> io::Result<Wrapped> {
+        let workdir = workdir.canonicalize()?;
+        let mut wrapped = Command::new(&self.binary);
+        wrapped.args(["--unshare-all", "--die-with-parent", "--cap-drop", "ALL"]);
+        for path in &self.readonly_paths {
+            wrapped.arg("--ro-bind-try").arg(path).arg(path);
+        }
+        wrapped
+            .arg("--bind")
+            .arg(&workdir)
+            .arg("/box")
+            .args(["--chdir", "/box", "--dev", "/dev", "--proc", "/proc"])
+            .args(["--tmpfs", "/tmp"])
+            .args(&self.extra_args)
+            .arg("--")
+            .args(program_and_args(command));
+        Ok(Wrapped {
+            command: wrapped,
+            cleanup: None,
+        })
+    }
+}
+
+pub struct Nsjail {
+    binary: PathBuf,
+    readonly_paths: Vec<PathBuf>,
+    extra_args: Vec<String>,
+}
+
+impl SandboxBackend for Nsjail {
+    fn wrap(&self, command: &Command, workdir: &Path, limits: &Limits) -> io::Result<Wrapped> {
+        let workdir = workdir.canonicalize()?;
+        let mut wrapped = Command::new(&self.binary);
+        // Resource limits are inherited from the runner rather than taken
+        // from nsjail's defaults, which cap file sizes at 1MB
+        wrapped
+            .args(["--mode", "o", "--quiet", "--keep_env", "--skip_setsid"])
+            .args(["--rlimit_as", "hard", "--rlimit_cpu", "hard"])
+            .args(["--rlimit_fsize", "hard", "--rlimit_nofile", "hard"])
+            .arg("--time_limit")
+            .arg(seconds(limits).to_string());
+        // Except for RLIMIT_NPROC, which nsjail applies inside its user
+        // namespace where it only counts the submission's processes
+        if let Some(processes) = limits.processes {
+            wrapped.arg("--rlimit_nproc").arg(processes.to_string());
+        }
+        for path in &self.readonly_paths {
+            if path.exists() {
+                wrapped.arg("--bindmount_ro").arg(path);
+            }
+        }
+        let mut bind = workdir.into_os_string();
+        bind.push(":/box");
+        wrapped
+            .arg("--bindmount")
+            .arg(bind)
+            .args(["--cwd", "/box", "--tmpfsmount", "/tmp"])
+            .args(&self.extra_args)
+            .arg("--")
+            .args(program_and_args(command));
+        Ok(Wrapped {
+            command: wrapped,
+            cleanup: None,
+        })
+    }
+}
+
+// isolate works with numbered boxes that have to be initialised before and
+// cleaned up after each run. It is setuid root, so we can't signal it and
+// rely on its own wall-clock limit instead.
+pub struct Isolate {
+    binary: PathBuf,
+    readonly_paths: Vec<PathBuf>,
+    extra_args: Vec<String>,
+}
+
+// isolate's default configuration allows box ids 0 to 999
+const ISOLATE_BOXES: u32 = 1000;
+static NEXT_BOX_ID: AtomicU32 = AtomicU32::new(0);
+
+impl SandboxBackend for Isolate {
+    fn wrap(&self, command: &Command, workdir: &Path, limits: &Limits) -> io::Result<Wrapped> {
+        let workdir = workdir.canonicalize()?;
+        let box_id = format!(
+            "--box-id={}",
+            NEXT_BOX_ID.fetch_add(1, Ordering::Relaxed) % ISOLATE_BOXES
+        );
+        let init = Command::new(&self.binary)
+            .args([box_id.as_str(), "--init"])
+            .output()?;
+        if !init.status.success() {
+            return Err(io::Error::other(format!(
+                "isolate --init failed :: {}",
+                String::from_utf8_lossy(&init.stderr)
+            )));
+        }
+
+        let mut wrapped = Command::new(&self.binary);
+        wrapped
+            .arg(&box_id)
+            .arg(format!("--wall-time={}", seconds(limits)))
+            .arg(format!("--time={}", seconds(limits)))
+            .arg("--env=PATH")
+            .arg("--env=HOME=/box");
+        if let Some(memory) = limits.memory_bytes {
+            wrapped.arg(format!("--mem={}", memory / 1024));
+        }
+        match limits.processes {
+            Some(processes) => wrapped.arg(format!("--processes={}", processes)),
+            None => wrapped.arg("--processes"),
+        };
+        if let Some(open_files) = limits.open_files {
+            wrapped.arg(format!("--open-files={}", open_files));
+        }
+        if let Some(file_size) = limits.file_size_bytes {
+            wrapped.arg(format!("--fsize={}", file_size / 1024));
+        }
+        for path in &self.readonly_paths {
+            let mut rule = OsString::from("--dir=");
+            rule.push(path);
+            rule.push(":maybe");
+            wrapped.arg(rule);
+        }
+        let mut bind = OsString::from("--dir=/submission=");
+        bind.push(&workdir);
+        bind.push(":rw");
+        wrapped
+            .arg(bind)
+            .arg("--chdir=/submission")
+            .args(&self.extra_args)
+            .arg("--run")
+            .arg("--")
+            .args(program_and_args(command));
+
+        let binary = self.binary.clone();
+        Ok(Wrapped {
+            command: wrapped,
+            cleanup: Some(Box::new(move || {
+                let cleanup = Command::new(&binary)
+                    .args([box_id.as_str(), "--cleanup"])
+                    .output();
+                if !cleanup.is_ok_and(|output| output.status.success()) {
+                    error!("Unable to clean up isolate box {}", box_id);
+                }
+            })),
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use log::{debug, error};
+use std::{
+    ffi::CString,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use uuid::Uuid;
+
+use crate::types::Limits;
+
+// Per-submission cgroup v2 used to enforce resource limits when the host
+// supports it. Submission cgroups are created under the configured cgroup
+// root, which has to be delegated to the server with the required
+// controllers enabled in `cgroup.subtree_control`.
+pub struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    pub fn create(root: &Path, limits: &Limits) -> Option<Cgroup> {
+        if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+            debug!("CGROUP_ROOT is set but the host is not using cgroup v2.");
+            return None;
+        }
+        let path = root.join(Uuid::new_v4().to_string());
+        if let Err(err) = fs::create_dir(&path) {
+            error!("Unable to create cgroup {} :: {}", path.display(), err);
+            return None;
+        }
+        let cgroup = Cgroup { path };
+        if let Some(limit) = limits.memory_bytes {
+            if let Err(err) = fs::write(cgroup.path.join("memory.max"), limit.to_string()) {
+                error!("Unable to set memory.max :: {}", err);
+                return None;
+            }
+            // Swapping would let the program go past its limit, but not
+            // every kernel is built with swap accounting
+            let _ = fs::write(cgroup.path.join("memory.swap.max"), "0");
+        }
+        if let Some(limit) = limits.processes {
+            if let Err(err) = fs::write(cgroup.path.join("pids.max"), limit.to_string()) {
+                error!("Unable to set pids.max :: {}", err);
+                return None;
+            }
+        }
+        Some(cgroup)
+    }
+
+    // Path of `cgroup.procs`, prepared up front so the child can join the
+    // cgroup between fork and exec without allocating.
+    pub fn procs_path(&self) -> Option<CString> {
+        let path = self.path.join("cgroup.procs");
+        CString::new(path.to_str()?).ok()
+    }
+
+    pub fn oom_killed(&self) -> bool {
+        self.event_seen("memory.events", "oom_kill")
+    }
+
+    // Counts the forks that failed because of pids.max
+    pub fn pids_limit_reached(&self) -> bool {
+        self.event_seen("pids.events", "max")
+    }
+
+    fn event_seen(&self, file: &str, event: &str) -> bool {
+        let events = match fs::read_to_string(self.path.join(file)) {
+            Ok(events) => events,
+            Err(_) => return false,
+        };
+        events.lines().any(|line| match line.split_once(' ') {
+            Some((name, count)) => name == event && count.trim() != "0",
+            None => false,
+        })
+    }
+
+    // Kills whatever is left in the cgroup, including processes that left
+    // the submission's process group. cgroup.kill needs Linux 5.14, on older
+    // kernels the cgroup is frozen so nothing can fork while its processes
+    // are signalled one by one.
+    pub fn kill(&self) {
+        if fs::write(self.path.join("cgroup.kill"), "1").is_ok() {
+            return;
+        }
+        let _ = fs::write(self.path.join("cgroup.freeze"), "1");
+        if let Ok(procs) = fs::read_to_string(self.path.join("cgroup.procs")) {
+            for pid in procs.lines().filter_map(|pid| pid.trim().parse().ok()) {
+                unsafe {
+                    libc::kill(pid, libc::SIGKILL);
+                }
+            }
+        }
+        let _ = fs::write(self.path.join("cgroup.freeze"), "0");
+    }
+
+    // memory.peak is only available since Linux 5.19
+    pub fn memory_peak(&self) -> Option<u64> {
+        let peak = fs::read_to_string(self.path.join("memory.peak")).ok()?;
+        peak.trim().parse().ok()
+    }
+
+    // A cgroup can only be removed once its last process has been reaped,
+    // which for reparented descendants happens asynchronously.
+    pub async fn remove(self) {
+        for _ in 0..10 {
+            if fs::remove_dir(&self.path).is_ok() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        error!("Unable to remove cgroup {}", self.path.display());
+    }
+}
+
+// Moves the calling process into the cgroup. Only async-signal-safe calls
+// are made, as this runs in the child between fork and exec.
+pub fn join(procs_path: &CString) -> std::io::Result<()> {
+    unsafe {
+        let fd = libc::open(procs_path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // Writing 0 moves the writer itself
+        let written = libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1);
+        libc::close(fd);
+        if written < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
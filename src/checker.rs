@@ -0,0 +1,236 @@
+use serde::Deserialize;
+
+use crate::types::{OutputDiff, Verdict};
+
+// How a testcase's output is compared with the expected one, picked per
+// question, e.g. `"checker": "tokens"` or
+// `"checker": {"float": {"abs_epsilon": 1e-4}}`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Checker {
+    // Byte for byte
+    Exact,
+    // Ignores whitespace around the whole output
+    #[default]
+    Trimmed,
+    // Ignores whitespace at the end of lines and blank lines at the end
+    TrailingWhitespace,
+    // Compares whitespace separated tokens, however they are spaced
+    Tokens,
+    // Like `trimmed`, ignoring case
+    CaseInsensitive,
+    // Like `tokens`, with numbers allowed to be off by either epsilon
+    Float {
+        #[serde(default = "default_epsilon")]
+        abs_epsilon: f64,
+        #[serde(default = "default_epsilon")]
+        rel_epsilon: f64,
+    },
+    // Like `trailing_whitespace`, for answers whose lines can be in any order
+    UnorderedLines,
+    // A checker program written by the problem setter, see `special_judge`
+    Program(CheckerProgram),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CheckerProgram {
+    pub language: String,
+    #[serde(default)]
+    pub language_version: Option<String>,
+    pub source_code: String,
+}
+
+// Outcome of checking a testcase
+pub struct Judgement {
+    pub verdict: Verdict,
+    // Between 0 and 1, only checker programs give partial scores
+    pub score: f64,
+    pub message: Option<String>,
+    // Only for wrong answers found by the built-in checkers
+    pub diff: Option<OutputDiff>,
+}
+
+impl From<Verdict> for Judgement {
+    fn from(verdict: Verdict) -> Judgement {
+        Judgement {
+            verdict,
+            score: if verdict == Verdict::Accepted {
+                1.0
+            } else {
+                0.0
+            },
+            message: None,
+            diff: None,
+        }
+    }
+}
+
+fn default_epsilon() -> f64 {
+    1e-6
+}
+
+// Lines without trailing whitespace, nor blank lines at the end
+pub(crate) fn lines(output: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+fn floats_match(actual: &str, expected: &str, abs_epsilon: f64, rel_epsilon: f64) -> bool {
+    if actual == expected {
+        return true;
+    }
+    match (actual.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(actual), Ok(expected)) if actual.is_finite() && expected.is_finite() => {
+            let difference = (actual - expected).abs();
+            difference <= abs_epsilon || difference <= rel_epsilon * expected.abs()
+        }
+        _ => false,
+    }
+}
+
+impl Checker {
+    pub fn check(&self, actual: &str, expected: &str) -> Verdict {
+        let matches = match self {
+            Checker::Exact => actual == expected,
+            Checker::Trimmed => actual.trim() == expected.trim(),
+            Checker::TrailingWhitespace => lines(actual) == lines(expected),
+            Checker::Tokens => actual.split_whitespace().eq(expected.split_whitespace()),
+            Checker::CaseInsensitive => {
+                actual.trim().to_lowercase() == expected.trim().to_lowercase()
+            }
+            Checker::Float {
+                abs_epsilon,
+                rel_epsilon,
+            } => {
+                let actual: Vec<&str> = actual.split_whitespace().collect();
+                let expected: Vec<&str> = expected.split_whitespace().collect();
+                actual.len() == expected.len()
+                    && actual.iter().zip(&expected).all(|(actual, expected)| {
+                        floats_match(actual, expected, *abs_epsilon, *rel_epsilon)
+                    })
+            }
+            Checker::UnorderedLines => {
+                let mut actual = lines(actual);
+                let mut expected = lines(expected);
+                actual.sort_unstable();
+                expected.sort_unstable();
+                actual == expected
+            }
+            // Run separately, as it takes the input too
+            Checker::Program(_) => false,
+        };
+        if matches {
+            Verdict::Accepted
+        } else {
+            Verdict::WrongAnswer
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float(abs_epsilon: f64, rel_epsilon: f64) -> Checker {
+        Checker::Float {
+            abs_epsilon,
+            rel_epsilon,
+        }
+    }
+
+    #[test]
+    fn exact_compares_bytes() {
+        assert_eq!(Checker::Exact.check("1 2\n", "1 2\n"), Verdict::Accepted);
+        assert_eq!(Checker::Exact.check("1 2", "1 2\n"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn trimmed_ignores_surrounding_whitespace() {
+        assert_eq!(
+            Checker::Trimmed.check("\n 42 \n\n", "42"),
+            Verdict::Accepted
+        );
+        assert_eq!(Checker::Trimmed.check("4 2", "42"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn trailing_whitespace_keeps_leading_whitespace() {
+        let checker = Checker::TrailingWhitespace;
+        assert_eq!(checker.check("a  \nb\t\n\n\n", "a\nb"), Verdict::Accepted);
+        assert_eq!(checker.check(" a\nb", "a\nb"), Verdict::WrongAnswer);
+        assert_eq!(checker.check("a\n\nb", "a\nb"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn tokens_ignore_spacing() {
+        let checker = Checker::Tokens;
+        assert_eq!(checker.check("1   2\n3", "1 2 3"), Verdict::Accepted);
+        assert_eq!(checker.check("1 2", "1 2 3"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let checker = Checker::CaseInsensitive;
+        assert_eq!(checker.check("YES\n", "yes"), Verdict::Accepted);
+        assert_eq!(checker.check("YES NO", "yes"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn float_absolute_epsilon() {
+        let checker = float(1e-3, 0.0);
+        assert_eq!(checker.check("0.1234", "0.1238"), Verdict::Accepted);
+        assert_eq!(checker.check("0.123", "0.125"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn float_relative_epsilon() {
+        let checker = float(0.0, 1e-6);
+        assert_eq!(checker.check("1000000.5", "1000000"), Verdict::Accepted);
+        assert_eq!(checker.check("1.5", "1"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn float_compares_other_tokens_exactly() {
+        let checker = float(1e-6, 1e-6);
+        assert_eq!(checker.check("YES 0.5", "YES 0.5000001"), Verdict::Accepted);
+        assert_eq!(checker.check("NO 0.5", "YES 0.5"), Verdict::WrongAnswer);
+        assert_eq!(checker.check("0.5", "0.5 0.5"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn float_rejects_non_finite_numbers() {
+        let checker = float(1.0, 1.0);
+        assert_eq!(checker.check("inf", "inf"), Verdict::Accepted);
+        assert_eq!(checker.check("nan", "1"), Verdict::WrongAnswer);
+        assert_eq!(checker.check("inf", "1e308"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn unordered_lines() {
+        let checker = Checker::UnorderedLines;
+        assert_eq!(checker.check("b\na  \nc\n\n", "a\nb\nc"), Verdict::Accepted);
+        assert_eq!(checker.check("a\na\nb", "a\nb\nb"), Verdict::WrongAnswer);
+        assert_eq!(checker.check("a b\nc", "a\nb c"), Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn checker_is_picked_from_json() {
+        let checker: Checker = serde_json::from_str(r#"{"float": {"abs_epsilon": 0.5}}"#).unwrap();
+        match checker {
+            Checker::Float {
+                abs_epsilon,
+                rel_epsilon,
+            } => {
+                assert_eq!(abs_epsilon, 0.5);
+                assert_eq!(rel_epsilon, default_epsilon());
+            }
+            _ => panic!("expected a float checker"),
+        }
+        let checker: Checker = serde_json::from_str(r#""unordered_lines""#).unwrap();
+        assert!(matches!(checker, Checker::UnorderedLines));
+    }
+}
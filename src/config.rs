@@ -0,0 +1,164 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    language::Languages,
+    special_judge::SpecialJudges,
+    types::{CodingError, Limits},
+};
+
+// Server wide settings, read once from the environment at startup.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxKind {
+    None,
+    Namespaces,
+    Bubblewrap,
+    Nsjail,
+    Isolate,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub sandbox: SandboxKind,
+    pub sandbox_readonly_paths: Vec<PathBuf>,
+    pub sandbox_binary: Option<PathBuf>,
+    pub sandbox_args: Vec<String>,
+    pub cgroup_root: Option<PathBuf>,
+    pub process_limit: u64,
+    pub open_files_limit: u64,
+    pub file_size_limit_bytes: u64,
+    pub output_limit_bytes: u64,
+    pub archive_size_limit_bytes: u64,
+    pub archive_entry_limit: u64,
+    pub diff_limit_bytes: usize,
+    // Largest `memory_limit_mb` a question can ask for
+    pub max_memory_limit_mb: u64,
+    pub problems_dir: Option<PathBuf>,
+    pub languages: Arc<Languages>,
+    // Compiled checker programs, kept for as long as the server runs
+    pub(crate) special_judges: Arc<SpecialJudges>,
+}
+
+const DEFAULT_READONLY_PATHS: &str = "/bin:/lib:/lib32:/lib64:/usr:/etc:/opt";
+// The JVM alone starts a few dozen threads, more on machines with many cores
+const DEFAULT_PROCESS_LIMIT: u64 = 128;
+const DEFAULT_OPEN_FILES_LIMIT: u64 = 256;
+const DEFAULT_FILE_SIZE_LIMIT_MB: u64 = 64;
+const DEFAULT_OUTPUT_LIMIT_KB: u64 = 1024;
+const DEFAULT_ARCHIVE_SIZE_LIMIT_MB: u64 = 16;
+const DEFAULT_ARCHIVE_ENTRY_LIMIT: u64 = 1000;
+const DEFAULT_DIFF_LIMIT_KB: u64 = 8;
+const DEFAULT_CHECKER_CACHE_SIZE: u64 = 32;
+const DEFAULT_MAX_MEMORY_LIMIT_MB: u64 = 4096;
+
+fn number_from_env(name: &str, default: u64) -> Result<u64, CodingError> {
+    match env::var(name) {
+        Err(_) => Ok(default),
+        Ok(value) => value.parse().map_err(|_| CodingError::InvalidConfig {
+            reason: format!("{} must be a number, got `{}`", name, value),
+        }),
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Config, CodingError> {
+        let sandbox = match env::var("SANDBOX").as_deref() {
+            Err(_) | Ok("none") => SandboxKind::None,
+            Ok("namespaces") => SandboxKind::Namespaces,
+            Ok("bwrap") => SandboxKind::Bubblewrap,
+            Ok("nsjail") => SandboxKind::Nsjail,
+            Ok("isolate") => SandboxKind::Isolate,
+            Ok(other) => {
+                return Err(CodingError::InvalidConfig {
+                    reason: format!("Unknown SANDBOX `{}`", other),
+                })
+            }
+        };
+        let sandbox_readonly_paths = env::var("SANDBOX_READONLY_PATHS")
+            .unwrap_or_else(|_| DEFAULT_READONLY_PATHS.to_string())
+            .split(':')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        let languages = match env::var("LANGUAGES_FILE") {
+            Ok(path) => Languages::load(Path::new(&path))?,
+            Err(_) => Languages::builtin(),
+        };
+        Ok(Config {
+            sandbox,
+            sandbox_readonly_paths,
+            sandbox_binary: env::var("SANDBOX_BINARY").ok().map(PathBuf::from),
+            sandbox_args: env::var("SANDBOX_ARGS")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            cgroup_root: env::var("CGROUP_ROOT").ok().map(PathBuf::from),
+            process_limit: number_from_env("PROCESS_LIMIT", DEFAULT_PROCESS_LIMIT)?,
+            open_files_limit: number_from_env("OPEN_FILES_LIMIT", DEFAULT_OPEN_FILES_LIMIT)?,
+            file_size_limit_bytes: number_from_env(
+                "FILE_SIZE_LIMIT_MB",
+                DEFAULT_FILE_SIZE_LIMIT_MB,
+            )? * 1024
+                * 1024,
+            output_limit_bytes: number_from_env("OUTPUT_LIMIT_KB", DEFAULT_OUTPUT_LIMIT_KB)? * 1024,
+            archive_size_limit_bytes: number_from_env(
+                "ARCHIVE_SIZE_LIMIT_MB",
+                DEFAULT_ARCHIVE_SIZE_LIMIT_MB,
+            )? * 1024
+                * 1024,
+            archive_entry_limit: number_from_env(
+                "ARCHIVE_ENTRY_LIMIT",
+                DEFAULT_ARCHIVE_ENTRY_LIMIT,
+            )?,
+            diff_limit_bytes: number_from_env("DIFF_LIMIT_KB", DEFAULT_DIFF_LIMIT_KB)? as usize
+                * 1024,
+            max_memory_limit_mb: number_from_env(
+                "MAX_MEMORY_LIMIT_MB",
+                DEFAULT_MAX_MEMORY_LIMIT_MB,
+            )?,
+            problems_dir: env::var("PROBLEMS_DIR").ok().map(PathBuf::from),
+            languages: Arc::new(languages),
+            special_judges: Arc::new(SpecialJudges::new(number_from_env(
+                "CHECKER_CACHE_SIZE",
+                DEFAULT_CHECKER_CACHE_SIZE,
+            )? as usize)),
+        })
+    }
+
+    // Finds out which toolchains submissions can use, which `from_env`
+    // leaves to this as it takes running them
+    pub async fn detect_languages(&mut self) {
+        let mut languages = Languages::clone(&self.languages);
+        languages.detect(self).await;
+        self.languages = Arc::new(languages);
+    }
+
+    // Whether anything holds submissions to `process_limit`. Root is exempt
+    // from RLIMIT_NPROC, except in isolate which runs them as its own user.
+    pub fn process_limit_enforced(&self) -> bool {
+        self.cgroup_root.is_some()
+            || match self.sandbox {
+                SandboxKind::None | SandboxKind::Bubblewrap => false,
+                SandboxKind::Namespaces | SandboxKind::Nsjail => (unsafe { libc::geteuid() }) != 0,
+                SandboxKind::Isolate => true,
+            }
+    }
+
+    // Limits for a single run, with the server wide ones filled in
+    pub fn limits(&self, time: Duration, memory_bytes: Option<u64>) -> Limits {
+        Limits {
+            time,
+            memory_bytes,
+            processes: Some(self.process_limit),
+            open_files: Some(self.open_files_limit),
+            file_size_bytes: Some(self.file_size_limit_bytes),
+            output_bytes: Some(self.output_limit_bytes),
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::types::{Diagnostic, Severity};
+
+// The ways compilers lay out their errors, picked per language
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticsFormat {
+    // gcc, g++, clang, swiftc, kotlinc, zig and most others
+    #[default]
+    Gcc,
+    Rustc,
+    Javac,
+    Mcs,
+}
+
+// Parses the stderr of a compiler into structured diagnostics.
+// Lines that don't look like a located diagnostic are ignored,
+// the raw text is always returned alongside in `compile_output`.
+pub fn parse(format: DiagnosticsFormat, stderr: &str) -> Vec<Diagnostic> {
+    match format {
+        DiagnosticsFormat::Rustc => parse_rustc(stderr),
+        DiagnosticsFormat::Javac => parse_javac(stderr),
+        DiagnosticsFormat::Mcs => parse_mcs(stderr),
+        DiagnosticsFormat::Gcc => parse_gcc(stderr),
+    }
+}
+
+fn severity(sev: &str) -> Severity {
+    match sev {
+        "warning" => Severity::Warning,
+        "note" | "help" => Severity::Note,
+        _ => Severity::Error,
+    }
+}
+
+// gcc, g++, clang, swiftc, kotlinc and zig all use
+// `file:line:column: severity: message`.
+fn parse_gcc(stderr: &str) -> Vec<Diagnostic> {
+    let re = Regex::new(
+        r"^(?P<file>[^:\s][^:]*):(?P<line>\d+):(?P<col>\d+): (?:fatal )?(?P<sev>error|warning|note): (?P<msg>.*)$",
+    )
+    .unwrap();
+    stderr
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| Diagnostic {
+            file: caps["file"].to_string(),
+            line: caps["line"].parse().unwrap_or(0),
+            column: caps["col"].parse().ok(),
+            severity: severity(&caps["sev"]),
+            message: caps["msg"].to_string(),
+        })
+        .collect()
+}
+
+// javac and scalac report `File.java:line: severity: message` without a column.
+fn parse_javac(stderr: &str) -> Vec<Diagnostic> {
+    let re =
+        Regex::new(r"^(?P<file>[^:\s][^:]*):(?P<line>\d+): (?P<sev>error|warning): (?P<msg>.*)$")
+            .unwrap();
+    stderr
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| Diagnostic {
+            file: caps["file"].to_string(),
+            line: caps["line"].parse().unwrap_or(0),
+            column: None,
+            severity: severity(&caps["sev"]),
+            message: caps["msg"].to_string(),
+        })
+        .collect()
+}
+
+// mcs reports `file(line,column): severity CODE: message`.
+fn parse_mcs(stderr: &str) -> Vec<Diagnostic> {
+    let re = Regex::new(
+        r"^(?P<file>[^(\s][^(]*)\((?P<line>\d+),(?P<col>\d+)\): (?P<sev>error|warning) (?P<msg>.*)$",
+    )
+    .unwrap();
+    stderr
+        .lines()
+        .filter_map(|line| re.captures(line))
+        .map(|caps| Diagnostic {
+            file: caps["file"].to_string(),
+            line: caps["line"].parse().unwrap_or(0),
+            column: caps["col"].parse().ok(),
+            severity: severity(&caps["sev"]),
+            message: caps["msg"].to_string(),
+        })
+        .collect()
+}
+
+// rustc prints the message on a header line and the location on the
+// following ` --> file:line:column` line.
+fn parse_rustc(stderr: &str) -> Vec<Diagnostic> {
+    let header = Regex::new(r"^(?P<sev>error|warning)(?:\[\w+\])?: (?P<msg>.*)$").unwrap();
+    let location = Regex::new(r"^\s*--> (?P<file>[^:]+):(?P<line>\d+):(?P<col>\d+)$").unwrap();
+
+    let mut diagnostics = Vec::new();
+    let mut pending: Option<(Severity, String)> = None;
+    for line in stderr.lines() {
+        if let Some(caps) = header.captures(line) {
+            pending = Some((severity(&caps["sev"]), caps["msg"].to_string()));
+        } else if let Some(caps) = location.captures(line) {
+            // Summary lines like "aborting due to previous error" have no location
+            if let Some((severity, message)) = pending.take() {
+                diagnostics.push(Diagnostic {
+                    file: caps["file"].to_string(),
+                    line: caps["line"].parse().unwrap_or(0),
+                    column: caps["col"].parse().ok(),
+                    severity,
+                    message,
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(
+        format: DiagnosticsFormat,
+        stderr: &str,
+    ) -> Vec<(String, u32, Option<u32>, Severity, String)> {
+        parse(format, stderr)
+            .into_iter()
+            .map(|d| (d.file, d.line, d.column, d.severity, d.message))
+            .collect()
+    }
+
+    fn diagnostic(
+        file: &str,
+        line: u32,
+        column: Option<u32>,
+        severity: Severity,
+        message: &str,
+    ) -> (String, u32, Option<u32>, Severity, String) {
+        (
+            file.to_string(),
+            line,
+            column,
+            severity,
+            message.to_string(),
+        )
+    }
+
+    #[test]
+    fn gcc() {
+        let stderr = "\
+main.c: In function 'main':
+main.c:2:13: warning: initialization of 'int' from 'char *' makes integer from pointer without a cast [-Wint-conversion]
+    2 |     int x = \"a\";
+      |             ^~~
+main.c:3:12: error: 'y' undeclared (first use in this function)
+    3 |     return y;
+      |            ^
+main.c:3:12: note: each undeclared identifier is reported only once for each function it appears in
+";
+        assert_eq!(
+            parsed(DiagnosticsFormat::Gcc, stderr),
+            [
+                diagnostic(
+                    "main.c",
+                    2,
+                    Some(13),
+                    Severity::Warning,
+                    "initialization of 'int' from 'char *' makes integer from pointer without a cast [-Wint-conversion]"
+                ),
+                diagnostic(
+                    "main.c",
+                    3,
+                    Some(12),
+                    Severity::Error,
+                    "'y' undeclared (first use in this function)"
+                ),
+                diagnostic(
+                    "main.c",
+                    3,
+                    Some(12),
+                    Severity::Note,
+                    "each undeclared identifier is reported only once for each function it appears in"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn gcc_fatal_error() {
+        let stderr = "\
+src/f.cpp:1:10: fatal error: missing.h: No such file or directory
+    1 | #include \"missing.h\"
+      |          ^~~~~~~~~~~
+compilation terminated.
+";
+        assert_eq!(
+            parsed(DiagnosticsFormat::Gcc, stderr),
+            [diagnostic(
+                "src/f.cpp",
+                1,
+                Some(10),
+                Severity::Error,
+                "missing.h: No such file or directory"
+            )]
+        );
+    }
+
+    #[test]
+    fn rustc() {
+        let stderr = "\
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+  |
+  = note: `#[warn(unused_variables)]` (part of `#[warn(unused)]`) on by default
+
+error[E0308]: mismatched types
+ --> src/main.rs:3:18
+  |
+3 |     let y: u32 = \"a\";
+  |            ---   ^^^ expected `u32`, found `&str`
+  |            |
+  |            expected due to this
+
+error: aborting due to 1 previous error; 1 warning emitted
+
+For more information about this error, try `rustc --explain E0308`.
+";
+        assert_eq!(
+            parsed(DiagnosticsFormat::Rustc, stderr),
+            [
+                diagnostic(
+                    "src/main.rs",
+                    2,
+                    Some(9),
+                    Severity::Warning,
+                    "unused variable: `x`"
+                ),
+                diagnostic(
+                    "src/main.rs",
+                    3,
+                    Some(18),
+                    Severity::Error,
+                    "mismatched types"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rustc_summary_without_a_location() {
+        let stderr = "\
+error: linking with `cc` failed: exit status: 1
+
+error: aborting due to 1 previous error
+";
+        assert!(parse(DiagnosticsFormat::Rustc, stderr).is_empty());
+    }
+
+    #[test]
+    fn javac() {
+        let stderr = "\
+Main.java:3: error: incompatible types: String cannot be converted to int
+        int x = \"a\";
+                ^
+com/acme/Util.java:4: error: cannot find symbol
+        foo();
+        ^
+  symbol:   method foo()
+  location: class Util
+2 errors
+";
+        assert_eq!(
+            parsed(DiagnosticsFormat::Javac, stderr),
+            [
+                diagnostic(
+                    "Main.java",
+                    3,
+                    None,
+                    Severity::Error,
+                    "incompatible types: String cannot be converted to int"
+                ),
+                diagnostic(
+                    "com/acme/Util.java",
+                    4,
+                    None,
+                    Severity::Error,
+                    "cannot find symbol"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn mcs() {
+        let stderr = "\
+Main.cs(5,13): error CS0103: The name `y' does not exist in the current context
+Main.cs(4,13): warning CS0219: The variable `x' is assigned but its value is never used
+Compilation failed: 1 error(s), 1 warnings
+";
+        assert_eq!(
+            parsed(DiagnosticsFormat::Mcs, stderr),
+            [
+                diagnostic(
+                    "Main.cs",
+                    5,
+                    Some(13),
+                    Severity::Error,
+                    "CS0103: The name `y' does not exist in the current context"
+                ),
+                diagnostic(
+                    "Main.cs",
+                    4,
+                    Some(13),
+                    Severity::Warning,
+                    "CS0219: The variable `x' is assigned but its value is never used"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_output_is_ignored() {
+        let stderr = "Note: Main.java uses unchecked or unsafe operations.\n";
+        for format in [
+            DiagnosticsFormat::Gcc,
+            DiagnosticsFormat::Rustc,
+            DiagnosticsFormat::Javac,
+            DiagnosticsFormat::Mcs,
+        ] {
+            assert!(parse(format, stderr).is_empty());
+            assert!(parse(format, "").is_empty());
+        }
+    }
+}
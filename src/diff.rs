@@ -0,0 +1,304 @@
+use std::fmt::Write;
+
+use crate::{checker::lines, types::OutputDiff};
+
+// Explains wrong answers: where the output first departs from the expected
+// one, and a unified diff of the two. Lines are compared without trailing
+// whitespace and trailing blank lines, as those rarely matter, unless that
+// leaves nothing to show.
+
+const CONTEXT_LINES: usize = 3;
+// Past this many pairs of lines, the part that changed is shown as replaced
+// as a whole rather than diffed line by line
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+#[derive(Clone, Copy)]
+enum Edit {
+    Equal(usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+// Cuts `text` down to at most `limit` bytes, on a character boundary
+fn cut(text: &str, limit: usize) -> (String, bool) {
+    if text.len() <= limit {
+        return (text.to_string(), false);
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (text[..end].to_string(), true)
+}
+
+// Shortest edit script turning `expected` into `actual`, through the longest
+// common subsequence of the lines that differ. Equal lines are given by
+// their index in `expected`, the others by their index in their side.
+fn edits(expected: &[&str], actual: &[&str]) -> Vec<Edit> {
+    let prefix = expected
+        .iter()
+        .zip(actual)
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+    let changed_expected = &expected[prefix..expected.len() - suffix];
+    let changed_actual = &actual[prefix..actual.len() - suffix];
+
+    let mut edits: Vec<Edit> = (0..prefix).map(Edit::Equal).collect();
+    let (mut i, mut j) = (0, 0);
+    if changed_expected.len().saturating_mul(changed_actual.len()) <= MAX_DIFF_CELLS {
+        // lcs[i * width + j] is the length of the longest common subsequence
+        // of changed_expected[i..] and changed_actual[j..]
+        let width = changed_actual.len() + 1;
+        let mut lcs = vec![0u32; (changed_expected.len() + 1) * width];
+        for i in (0..changed_expected.len()).rev() {
+            for j in (0..changed_actual.len()).rev() {
+                lcs[i * width + j] = if changed_expected[i] == changed_actual[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        while i < changed_expected.len() && j < changed_actual.len() {
+            if changed_expected[i] == changed_actual[j] {
+                edits.push(Edit::Equal(prefix + i));
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                edits.push(Edit::Delete(prefix + i));
+                i += 1;
+            } else {
+                edits.push(Edit::Insert(prefix + j));
+                j += 1;
+            }
+        }
+    }
+    edits.extend((i..changed_expected.len()).map(|i| Edit::Delete(prefix + i)));
+    edits.extend((j..changed_actual.len()).map(|j| Edit::Insert(prefix + j)));
+    edits.extend((expected.len() - suffix..expected.len()).map(Edit::Equal));
+    edits
+}
+
+// `diff -u` style hunks, with a few lines of context around the changes
+fn unified(expected: &[&str], actual: &[&str], edits: &[Edit]) -> String {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (position, edit) in edits.iter().enumerate() {
+        if let Edit::Equal(_) = edit {
+            continue;
+        }
+        let start = position.saturating_sub(CONTEXT_LINES);
+        let end = (position + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // Lines of each side before every edit
+    let mut before = Vec::with_capacity(edits.len());
+    let (mut expected_line, mut actual_line) = (0, 0);
+    for edit in edits {
+        before.push((expected_line, actual_line));
+        match edit {
+            Edit::Equal(_) => {
+                expected_line += 1;
+                actual_line += 1;
+            }
+            Edit::Delete(_) => expected_line += 1,
+            Edit::Insert(_) => actual_line += 1,
+        }
+    }
+    before.push((expected_line, actual_line));
+
+    let mut out = String::from("--- expected\n+++ actual\n");
+    for (start, end) in hunks {
+        let (expected_start, actual_start) = before[start];
+        let expected_count = before[end].0 - expected_start;
+        let actual_count = before[end].1 - actual_start;
+        // Empty ranges point at the line before them
+        let first = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+        let _ = writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            first(expected_start, expected_count),
+            expected_count,
+            first(actual_start, actual_count),
+            actual_count
+        );
+        for edit in &edits[start..end] {
+            let _ = match *edit {
+                Edit::Equal(line) => writeln!(out, " {}", expected[line]),
+                Edit::Delete(line) => writeln!(out, "-{}", expected[line]),
+                Edit::Insert(line) => writeln!(out, "+{}", actual[line]),
+            };
+        }
+    }
+    out
+}
+
+// None when the outputs don't differ line by line at all. `limit` caps the
+// size of the diff and of each of the lines shown.
+pub fn diff(expected: &str, actual: &str, limit: usize) -> Option<OutputDiff> {
+    let (mut expected_lines, mut actual_lines) = (lines(expected), lines(actual));
+    if expected_lines == actual_lines {
+        expected_lines = expected.split('\n').collect();
+        actual_lines = actual.split('\n').collect();
+        if expected_lines == actual_lines {
+            return None;
+        }
+    }
+    let line = expected_lines
+        .iter()
+        .zip(&actual_lines)
+        .take_while(|(expected, actual)| expected == actual)
+        .count();
+    let expected_line = expected_lines.get(line).copied();
+    let actual_line = actual_lines.get(line).copied();
+    let column = match (expected_line, actual_line) {
+        (Some(expected), Some(actual)) => {
+            expected
+                .chars()
+                .zip(actual.chars())
+                .take_while(|(expected, actual)| expected == actual)
+                .count()
+                + 1
+        }
+        _ => 1,
+    };
+
+    let edits = edits(&expected_lines, &actual_lines);
+    let (unified, truncated) = cut(&unified(&expected_lines, &actual_lines, &edits), limit);
+    Some(OutputDiff {
+        line: line + 1,
+        column,
+        expected_line: expected_line.map(|line| cut(line, limit).0),
+        actual_line: actual_line.map(|line| cut(line, limit).0),
+        unified,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_output_has_no_diff() {
+        assert!(diff("1\n2\n", "1\n2\n", 1024).is_none());
+        assert!(diff("", "", 1024).is_none());
+    }
+
+    #[test]
+    fn trailing_whitespace_only_differences_are_still_shown() {
+        let diff = diff("1\n2", "1\n2 ", 1024).unwrap();
+        assert_eq!(diff.line, 2);
+        assert_eq!(diff.column, 2);
+        assert_eq!(
+            diff.unified,
+            "--- expected\n+++ actual\n@@ -1,2 +1,2 @@\n 1\n-2\n+2 \n"
+        );
+    }
+
+    #[test]
+    fn changed_line() {
+        let diff = diff("a\nb\nc\n", "a\nx\nc\n", 1024).unwrap();
+        assert_eq!(diff.line, 2);
+        assert_eq!(diff.column, 1);
+        assert_eq!(diff.expected_line.as_deref(), Some("b"));
+        assert_eq!(diff.actual_line.as_deref(), Some("x"));
+        assert_eq!(
+            diff.unified,
+            "--- expected\n+++ actual\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n"
+        );
+        assert!(!diff.truncated);
+    }
+
+    #[test]
+    fn column_is_counted_in_characters() {
+        let diff = diff("héllo world", "héllo there", 1024).unwrap();
+        assert_eq!(diff.line, 1);
+        assert_eq!(diff.column, 7);
+    }
+
+    #[test]
+    fn missing_and_extra_lines() {
+        let missing = diff("a\nb\nc", "a\nb", 1024).unwrap();
+        assert_eq!(missing.line, 3);
+        assert_eq!(missing.expected_line.as_deref(), Some("c"));
+        assert_eq!(missing.actual_line, None);
+        assert!(missing.unified.contains("@@ -1,3 +1,2 @@\n a\n b\n-c\n"));
+
+        let extra = diff("", "x", 1024).unwrap();
+        assert_eq!(extra.line, 1);
+        assert_eq!(extra.expected_line, None);
+        assert!(extra.unified.contains("@@ -0,0 +1,1 @@\n+x\n"));
+    }
+
+    #[test]
+    fn distant_changes_get_their_own_hunks() {
+        let expected: Vec<String> = (1..=10).map(|i| i.to_string()).collect();
+        let mut actual = expected.clone();
+        actual[0] = "X".to_string();
+        actual[9] = "Y".to_string();
+        let diff = diff(&expected.join("\n"), &actual.join("\n"), 1024).unwrap();
+        let headers: Vec<&str> = diff
+            .unified
+            .lines()
+            .filter(|line| line.starts_with("@@"))
+            .collect();
+        assert_eq!(headers, ["@@ -1,4 +1,4 @@", "@@ -7,4 +7,4 @@"]);
+    }
+
+    #[test]
+    fn edits_keep_common_lines() {
+        let edits = edits(&["a", "b", "c", "d"], &["a", "c", "d", "e"]);
+        let script: Vec<String> = edits
+            .iter()
+            .map(|edit| match edit {
+                Edit::Equal(line) => format!("={}", line),
+                Edit::Delete(line) => format!("-{}", line),
+                Edit::Insert(line) => format!("+{}", line),
+            })
+            .collect();
+        assert_eq!(script, ["=0", "-1", "=2", "=3", "+3"]);
+    }
+
+    #[test]
+    fn large_changes_are_replaced_as_a_whole() {
+        let expected: Vec<String> = (0..1001).map(|i| format!("a{}", i)).collect();
+        let actual: Vec<String> = (0..1001).map(|i| format!("b{}", i)).collect();
+        let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+        let actual: Vec<&str> = actual.iter().map(String::as_str).collect();
+        let edits = edits(&expected, &actual);
+        assert_eq!(edits.len(), 2002);
+        assert!(edits[..1001]
+            .iter()
+            .all(|edit| matches!(edit, Edit::Delete(_))));
+        assert!(edits[1001..]
+            .iter()
+            .all(|edit| matches!(edit, Edit::Insert(_))));
+    }
+
+    #[test]
+    fn cut_stops_on_a_character_boundary() {
+        assert_eq!(cut("abc", 3), ("abc".to_string(), false));
+        assert_eq!(cut("aé", 2), ("a".to_string(), true));
+        assert_eq!(cut("ééé", 5), ("éé".to_string(), true));
+    }
+
+    #[test]
+    fn diff_is_truncated_at_the_limit() {
+        let expected = "é".repeat(100);
+        let diff = diff(&expected, "x", 33).unwrap();
+        assert!(diff.truncated);
+        assert!(diff.unified.len() <= 33);
+        assert_eq!(diff.expected_line.as_deref(), Some("é".repeat(16).as_str()));
+        assert_eq!(diff.actual_line.as_deref(), Some("x"));
+    }
+}
@@ -0,0 +1,693 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt::{self, Write};
+
+use crate::{language::Language, types::CodingError};
+
+// LeetCode style problems, where candidates only write a function. The
+// harness wraps it in a driver for the language, which reads the arguments
+// from stdin, calls the function and prints what it returns as JSON on the
+// last line of stdout.
+//
+// Testcase inputs hold the arguments as JSON values, one after the other,
+// and expected outputs the JSON of the returned value. Before reaching the
+// driver, inputs are checked against the parameter types and encoded as
+// whitespace separated tokens: a number for ints, the length in bytes, a
+// space and the bytes for strings, and the length followed by the items for
+// lists.
+//
+// Drivers keep the candidate's code on its own line numbers, so compiler
+// errors and stack traces point at the lines they wrote. Python is the
+// exception, as `from typing import *` needs a line of its own: its
+// tracebacks are one line ahead.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Harness {
+    pub function: String,
+    pub params: Vec<Param>,
+    pub returns: Type,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: Type,
+}
+
+// Written as in LeetCode: `int`, `string`, `int[]`, `string[][]`...
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Type {
+    Int,
+    String,
+    List(Box<Type>),
+}
+
+impl TryFrom<String> for Type {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Type, String> {
+        match value.as_str() {
+            "int" => Ok(Type::Int),
+            "string" => Ok(Type::String),
+            _ => match value.strip_suffix("[]") {
+                Some(inner) => Ok(Type::List(Box::new(Type::try_from(inner.to_string())?))),
+                None => Err(format!(
+                    "Unknown type `{}`, expected int, string or a list like int[]",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::String => write!(f, "string"),
+            Type::List(inner) => write!(f, "{}[]", inner),
+        }
+    }
+}
+
+impl Type {
+    fn java(&self) -> String {
+        match self {
+            Type::Int => String::from("int"),
+            Type::String => String::from("String"),
+            Type::List(inner) => format!("{}[]", inner.java()),
+        }
+    }
+
+    fn cpp(&self) -> String {
+        match self {
+            Type::Int => String::from("int"),
+            Type::String => String::from("std::string"),
+            Type::List(inner) => format!("std::vector<{}>", inner.cpp()),
+        }
+    }
+}
+
+// Checks a value against its type and appends its tokens
+fn encode(value: &Value, kind: &Type, out: &mut String) -> Result<(), String> {
+    match (kind, value) {
+        (Type::Int, Value::Number(number)) => match number.as_i64() {
+            Some(int) if i32::try_from(int).is_ok() => {
+                let _ = write!(out, "{} ", int);
+            }
+            _ => return Err(format!("{} does not fit in an int", number)),
+        },
+        (Type::String, Value::String(string)) => {
+            let _ = write!(out, "{} {} ", string.len(), string);
+        }
+        (Type::List(inner), Value::Array(items)) => {
+            let _ = write!(out, "{} ", items.len());
+            for item in items {
+                encode(item, inner, out)?;
+            }
+        }
+        _ => return Err(format!("expected {}, got {}", kind, value)),
+    }
+    Ok(())
+}
+
+fn invalid(index: usize, reason: String) -> CodingError {
+    CodingError::InvalidSubmission {
+        reason: format!("Testcase {} :: {}", index, reason),
+    }
+}
+
+impl Harness {
+    // The arguments as the driver reads them, from their JSON values
+    fn arguments(&self, input: &str) -> Result<String, String> {
+        let values = serde_json::Deserializer::from_str(input)
+            .into_iter::<Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("Invalid input :: {}", err))?;
+        if values.len() != self.params.len() {
+            return Err(format!(
+                "Expected {} arguments, got {}",
+                self.params.len(),
+                values.len()
+            ));
+        }
+        let mut tokens = String::new();
+        for (param, value) in self.params.iter().zip(&values) {
+            encode(value, &param.kind, &mut tokens)
+                .map_err(|reason| format!("`{}` :: {}", param.name, reason))?;
+        }
+        tokens.push('\n');
+        Ok(tokens)
+    }
+
+    // Testcases as the driver reads them, with the expected outputs in the
+    // same form as `Harness::result`
+    pub fn testcases(
+        &self,
+        testcases: &[(String, String)],
+    ) -> Result<Vec<(String, String)>, CodingError> {
+        let mut encoded = Vec::with_capacity(testcases.len());
+        for (index, (input, expected_output)) in testcases.iter().enumerate() {
+            let tokens = self
+                .arguments(input)
+                .map_err(|reason| invalid(index, reason))?;
+            let expected: Value = serde_json::from_str(expected_output)
+                .map_err(|err| invalid(index, format!("Invalid expected output :: {}", err)))?;
+            encode(&expected, &self.returns, &mut String::new())
+                .map_err(|reason| invalid(index, format!("Expected output :: {}", reason)))?;
+            encoded.push((tokens, expected.to_string()));
+        }
+        Ok(encoded)
+    }
+
+    // Custom input, given as the JSON arguments like testcase inputs
+    pub fn input(&self, input: &str) -> Result<String, CodingError> {
+        self.arguments(input)
+            .map_err(|reason| CodingError::InvalidSubmission {
+                reason: format!("Custom input :: {}", reason),
+            })
+    }
+
+    // The returned value, from the last line of stdout so that candidates
+    // can still print while debugging. Formatting differences between
+    // languages are evened out by going through JSON.
+    pub fn result(&self, stdout: &str) -> String {
+        let line = stdout
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default();
+        match serde_json::from_str::<Value>(line) {
+            Ok(value) => value.to_string(),
+            Err(_) => line.to_string(),
+        }
+    }
+
+    // The candidate's code together with the driver calling it
+    pub fn wrap(&self, language: &dyn Language, source_code: &str) -> Result<String, CodingError> {
+        let identifier = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        if !identifier(&self.function) {
+            return Err(CodingError::InvalidSubmission {
+                reason: format!("`{}` is not a valid function name", self.function),
+            });
+        }
+        let (name, driver) = match DRIVERS
+            .iter()
+            .find(|(name, _)| language.harness() == Some(*name))
+        {
+            Some(driver) => driver,
+            None => {
+                return Err(CodingError::InvalidSubmission {
+                    reason: format!("There is no harness for {}", language.name()),
+                })
+            }
+        };
+        // A Java file can only have one public class, which names the file
+        // and has to be the driver's
+        let source_code = match *name {
+            "java" => Regex::new(
+                r"\bpublic\s+((?:(?:abstract|final)\s+)?(?:class|interface|enum|record)\b)",
+            )
+            .unwrap()
+            .replace_all(source_code, "$1"),
+            _ => source_code.into(),
+        };
+        let kinds: Vec<String> = self
+            .params
+            .iter()
+            .map(|param| param.kind.to_string())
+            .collect();
+        let args: Vec<String> = (0..self.params.len())
+            .map(|index| format!("arg{}", index))
+            .collect();
+        Ok(driver
+            .replace("$TYPES", &serde_json::to_string(&kinds).unwrap_or_default())
+            .replace("$JAVA_READERS", &self.java_readers())
+            .replace(
+                "$JAVA_READ",
+                &self.read(|kind, arg| {
+                    format!("        {} {} = {};\n", kind.java(), arg, java_reader(kind))
+                }),
+            )
+            .replace(
+                "$CPP_READ",
+                &self.read(|kind, arg| {
+                    format!("    {} {};\n    harness::read({});\n", kind.cpp(), arg, arg)
+                }),
+            )
+            .replace("$ARGS", &args.join(", "))
+            .replace("$FUNCTION", &self.function)
+            .replace("$SOURCE", &source_code))
+    }
+
+    fn read(&self, statement: impl Fn(&Type, &str) -> String) -> String {
+        self.params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| statement(&param.kind, &format!("arg{}", index)))
+            .collect()
+    }
+
+    // Java can't overload on array types, so every list type the
+    // parameters use gets its own reader
+    fn java_readers(&self) -> String {
+        let mut lists = Vec::new();
+        for param in &self.params {
+            let mut kind = &param.kind;
+            while let Type::List(inner) = kind {
+                if !lists.contains(&kind) {
+                    lists.push(kind);
+                }
+                kind = inner;
+            }
+        }
+        let mut readers = String::new();
+        for list in lists {
+            let Type::List(inner) = list else { continue };
+            // Only the outer dimension is sized, e.g. `new int[n][]`
+            let kind = list.java();
+            let (base, dimensions) = kind.split_once("[]").unwrap_or_default();
+            let _ = write!(
+                readers,
+                r#"
+    private static {kind} {reader}() {{
+        {kind} value = new {base}[readInt()]{dimensions};
+        for (int i = 0; i < value.length; i++) {{
+            value[i] = {inner};
+        }}
+        return value;
+    }}
+"#,
+                kind = kind,
+                reader = java_reader(list).trim_end_matches("()"),
+                base = base,
+                dimensions = dimensions,
+                inner = java_reader(inner),
+            );
+        }
+        readers
+    }
+}
+
+// Call reading a value of the type, e.g. `readStringList2()` for string[][]
+fn java_reader(kind: &Type) -> String {
+    let mut depth = 0;
+    let mut base = kind;
+    while let Type::List(inner) = base {
+        depth += 1;
+        base = inner;
+    }
+    let name = match base {
+        Type::String => "String",
+        _ => "Int",
+    };
+    match depth {
+        0 => format!("read{}()", name),
+        _ => format!("read{}List{}()", name, depth),
+    }
+}
+
+// Drivers by the name languages refer to them with
+pub const DRIVERS: &[(&str, &str)] = &[
+    ("python", PYTHON),
+    ("javascript", JAVASCRIPT),
+    ("java", JAVA),
+    ("cpp", CPP),
+];
+
+const PYTHON: &str = r#"from typing import *
+$SOURCE
+
+
+def _main():
+    import json
+    import sys
+
+    data = sys.stdin.buffer.read()
+    pos = 0
+
+    def read_int():
+        nonlocal pos
+        while data[pos:pos + 1].isspace():
+            pos += 1
+        end = pos
+        while end < len(data) and not data[end:end + 1].isspace():
+            end += 1
+        value = int(data[pos:end])
+        pos = end
+        return value
+
+    def read(kind):
+        nonlocal pos
+        if kind.endswith("[]"):
+            return [read(kind[:-2]) for _ in range(read_int())]
+        if kind == "int":
+            return read_int()
+        length = read_int()
+        value = data[pos + 1:pos + 1 + length].decode()
+        pos += 1 + length
+        return value
+
+    args = [read(kind) for kind in $TYPES]
+    result = Solution().$FUNCTION(*args)
+    sys.stdout.write("\n" + json.dumps(result, separators=(",", ":"), ensure_ascii=False) + "\n")
+
+
+_main()
+"#;
+
+const JAVASCRIPT: &str = r#"$SOURCE
+
+(() => {
+  const data = require("fs").readFileSync(0);
+  let pos = 0;
+  const isSpace = (byte) => byte === 32 || (byte >= 9 && byte <= 13);
+
+  const readInt = () => {
+    while (pos < data.length && isSpace(data[pos])) pos++;
+    const start = pos;
+    while (pos < data.length && !isSpace(data[pos])) pos++;
+    return Number(data.toString("latin1", start, pos));
+  };
+
+  const read = (kind) => {
+    if (kind.endsWith("[]")) {
+      const length = readInt();
+      const list = [];
+      for (let i = 0; i < length; i++) list.push(read(kind.slice(0, -2)));
+      return list;
+    }
+    if (kind === "int") return readInt();
+    const length = readInt();
+    const value = data.toString("utf8", pos + 1, pos + 1 + length);
+    pos += 1 + length;
+    return value;
+  };
+
+  const result = $FUNCTION(...$TYPES.map(read));
+  process.stdout.write("\n" + JSON.stringify(result) + "\n");
+})();
+"#;
+
+const JAVA: &str = r#"import java.util.*; import java.io.*; $SOURCE
+
+public class Main {
+    private static byte[] data;
+    private static int pos = 0;
+
+    private static int readInt() {
+        while (pos < data.length && Character.isWhitespace(data[pos])) pos++;
+        int start = pos;
+        while (pos < data.length && !Character.isWhitespace(data[pos])) pos++;
+        return Integer.parseInt(new String(data, start, pos - start));
+    }
+
+    private static String readString() {
+        int length = readInt();
+        String value = new String(data, pos + 1, length, java.nio.charset.StandardCharsets.UTF_8);
+        pos += 1 + length;
+        return value;
+    }
+$JAVA_READERS
+    private static void write(StringBuilder out, Object value) {
+        if (value instanceof String) {
+            out.append('"');
+            for (char c : ((String) value).toCharArray()) {
+                if (c == '"' || c == '\\') out.append('\\').append(c);
+                // Escaped so the output doesn't depend on the default charset
+                else if (c < 0x20 || c > 0x7e) out.append(String.format("\\u%04x", (int) c));
+                else out.append(c);
+            }
+            out.append('"');
+        } else if (value != null && value.getClass().isArray()) {
+            out.append('[');
+            for (int i = 0; i < java.lang.reflect.Array.getLength(value); i++) {
+                if (i > 0) out.append(',');
+                write(out, java.lang.reflect.Array.get(value, i));
+            }
+            out.append(']');
+        } else if (value instanceof Iterable) {
+            out.append('[');
+            boolean first = true;
+            for (Object item : (Iterable<?>) value) {
+                if (!first) out.append(',');
+                write(out, item);
+                first = false;
+            }
+            out.append(']');
+        } else {
+            out.append(value);
+        }
+    }
+
+    public static void main(String[] args) throws IOException {
+        data = System.in.readAllBytes();
+$JAVA_READ
+        StringBuilder out = new StringBuilder();
+        write(out, new Solution().$FUNCTION($ARGS));
+        System.out.println();
+        System.out.println(out);
+    }
+}
+"#;
+
+const CPP: &str = r#"#include <bits/stdc++.h>
+using namespace std;
+#line 1
+$SOURCE
+
+namespace harness {
+std::string data;
+size_t pos = 0;
+
+long long read_number() {
+    while (pos < data.size() && isspace((unsigned char)data[pos])) pos++;
+    size_t start = pos;
+    while (pos < data.size() && !isspace((unsigned char)data[pos])) pos++;
+    return std::stoll(data.substr(start, pos - start));
+}
+
+void read(int &value) { value = (int)read_number(); }
+
+void read(std::string &value) {
+    size_t length = read_number();
+    value = data.substr(pos + 1, length);
+    pos += 1 + length;
+}
+
+template <typename T> void read(std::vector<T> &value) {
+    value.resize(read_number());
+    for (auto &item : value) read(item);
+}
+
+void write(int value) { std::cout << value; }
+
+void write(const std::string &value) {
+    std::cout << '"';
+    for (unsigned char c : value) {
+        if (c == '"' || c == '\\') {
+            std::cout << '\\' << c;
+        } else if (c < 0x20) {
+            char escaped[8];
+            snprintf(escaped, sizeof escaped, "\\u%04x", c);
+            std::cout << escaped;
+        } else {
+            std::cout << c;
+        }
+    }
+    std::cout << '"';
+}
+
+template <typename T> void write(const std::vector<T> &value) {
+    std::cout << '[';
+    for (size_t i = 0; i < value.size(); i++) {
+        if (i > 0) std::cout << ',';
+        write(value[i]);
+    }
+    std::cout << ']';
+}
+}
+
+int main() {
+    harness::data.assign(std::istreambuf_iterator<char>(std::cin), std::istreambuf_iterator<char>());
+$CPP_READ
+    auto result = Solution().$FUNCTION($ARGS);
+    std::cout << std::endl;
+    harness::write(result);
+    std::cout << std::endl;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{language::Languages, testing::reason};
+    use serde_json::json;
+
+    fn kind(name: &str) -> Type {
+        Type::try_from(name.to_string()).unwrap()
+    }
+
+    fn encoded(value: Value, name: &str) -> Result<String, String> {
+        let mut out = String::new();
+        encode(&value, &kind(name), &mut out).map(|()| out)
+    }
+
+    fn harness() -> Harness {
+        serde_json::from_value(json!({
+            "function": "twoSum",
+            "params": [
+                {"name": "nums", "type": "int[]"},
+                {"name": "target", "type": "int"}
+            ],
+            "returns": "int[]"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_types() {
+        assert_eq!(kind("int"), Type::Int);
+        assert_eq!(kind("string"), Type::String);
+        assert_eq!(kind("int[]"), Type::List(Box::new(Type::Int)));
+        assert_eq!(
+            kind("string[][]"),
+            Type::List(Box::new(Type::List(Box::new(Type::String))))
+        );
+        for name in ["long", "int[", "Int", ""] {
+            assert_eq!(
+                Type::try_from(name.to_string()),
+                Err(format!(
+                    "Unknown type `{}`, expected int, string or a list like int[]",
+                    name
+                ))
+            );
+        }
+        // The innermost type is the one named
+        assert_eq!(
+            Type::try_from("long[]".to_string()),
+            Type::try_from("long".to_string())
+        );
+        assert!(Type::try_from("[]".to_string()).is_err());
+    }
+
+    #[test]
+    fn names_types_per_language() {
+        let grid = kind("string[][]");
+        assert_eq!(grid.to_string(), "string[][]");
+        assert_eq!(grid.java(), "String[][]");
+        assert_eq!(grid.cpp(), "std::vector<std::vector<std::string>>");
+    }
+
+    #[test]
+    fn encodes_ints() {
+        assert_eq!(encoded(json!(-7), "int").unwrap(), "-7 ");
+        assert_eq!(
+            encoded(json!(i32::MAX as i64 + 1), "int"),
+            Err("2147483648 does not fit in an int".to_string())
+        );
+        assert_eq!(
+            encoded(json!(1.5), "int"),
+            Err("1.5 does not fit in an int".to_string())
+        );
+    }
+
+    #[test]
+    fn encodes_strings_with_their_length_in_bytes() {
+        assert_eq!(encoded(json!("héllo"), "string").unwrap(), "6 héllo ");
+        assert_eq!(encoded(json!("a b"), "string").unwrap(), "3 a b ");
+        assert_eq!(encoded(json!(""), "string").unwrap(), "0  ");
+    }
+
+    #[test]
+    fn encodes_lists() {
+        assert_eq!(
+            encoded(json!([[1, 2], [], [3]]), "int[][]").unwrap(),
+            "3 2 1 2 0 1 3 "
+        );
+        assert_eq!(
+            encoded(json!([1, "2"]), "int[]"),
+            Err("expected int, got \"2\"".to_string())
+        );
+        assert_eq!(
+            encoded(json!("12"), "int[]"),
+            Err("expected int[], got \"12\"".to_string())
+        );
+    }
+
+    #[test]
+    fn encodes_testcases() {
+        let testcases = vec![("[2, 7, 11, 15]\n9".to_string(), "[0,  1]".to_string())];
+        assert_eq!(
+            harness().testcases(&testcases).unwrap(),
+            [("4 2 7 11 15 9 \n".to_string(), "[0,1]".to_string())]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_testcases() {
+        let testcase = |input: &str, output: &str| vec![(input.to_string(), output.to_string())];
+        let harness = harness();
+        assert_eq!(
+            reason(harness.testcases(&testcase("[1]", "[]"))),
+            "Testcase 0 :: Expected 2 arguments, got 1"
+        );
+        assert_eq!(
+            reason(harness.testcases(&testcase("[1] \"9\"", "[]"))),
+            "Testcase 0 :: `target` :: expected int, got \"9\""
+        );
+        assert!(reason(harness.testcases(&testcase("[1", "[]")))
+            .starts_with("Testcase 0 :: Invalid input :: "));
+        assert_eq!(
+            reason(harness.testcases(&testcase("[1] 9", "0"))),
+            "Testcase 0 :: Expected output :: expected int[], got 0"
+        );
+        assert_eq!(
+            reason(harness.input("[1, 2]")),
+            "Custom input :: Expected 2 arguments, got 1"
+        );
+    }
+
+    #[test]
+    fn result_is_the_last_line_as_json() {
+        let harness = harness();
+        assert_eq!(harness.result("debug\n[0, 1]\n\n"), "[0,1]");
+        assert_eq!(harness.result("not json"), "not json");
+        assert_eq!(harness.result(""), "");
+    }
+
+    #[test]
+    fn wraps_java_without_a_second_public_class() {
+        let java = Languages::builtin().get("java", None).unwrap();
+        let source = "import java.util.*;\npublic class Solution {\n    public int[] twoSum(int[] nums, int target) { return nums; }\n}\n";
+        let wrapped = harness().wrap(java.as_ref(), source).unwrap();
+        assert!(wrapped.contains("\nclass Solution {"));
+        assert!(wrapped.contains("public int[] twoSum"));
+        // The candidate's code starts on the first line
+        assert!(wrapped
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with("import java.util.*;"));
+    }
+
+    #[test]
+    fn rejects_bad_function_names() {
+        let python = Languages::builtin().get("python", None).unwrap();
+        let mut harness = harness();
+        harness.function = "two sum".to_string();
+        assert_eq!(
+            reason(harness.wrap(python.as_ref(), "")),
+            "`two sum` is not a valid function name"
+        );
+    }
+}
@@ -0,0 +1,850 @@
+use futures_util::future::join_all;
+use log::{error, warn};
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap, fmt, fs, path::Path, process::Command, sync::Arc, time::Duration,
+};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    diagnostics::DiagnosticsFormat,
+    harness::DRIVERS,
+    runner,
+    types::{CodingError, LanguageInfo},
+};
+
+// Everything the server knows about a language: how its source file is
+// named, how it is compiled and how it is run. Commands are returned as
+// argv vectors and run with the submission folder as working directory.
+pub trait Language: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Set for languages available in several versions, like C++17 and
+    // C++20, except for the default one
+    fn language_version(&self) -> Option<&str> {
+        None
+    }
+
+    // Other names the language can be requested by
+    fn aliases(&self) -> &[String] {
+        &[]
+    }
+
+    fn source_file_name(&self, source_code: &str) -> Result<String, CodingError>;
+
+    fn is_compiled(&self) -> bool;
+
+    // Only called for compiled languages
+    fn compile_command(&self, sources: &Sources) -> Result<Vec<String>, CodingError>;
+
+    fn run_command(&self, sources: &Sources) -> Result<Vec<String>, CodingError>;
+
+    // Scales the time limit of the question, for slow runtimes
+    fn time_multiplier(&self) -> f64 {
+        1.0
+    }
+
+    // Prints the toolchain's version, which is also how we find out at
+    // startup whether it is installed at all
+    fn version_command(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    // Driver wrapping candidates' functions for questions with a harness,
+    // one of `harness::DRIVERS`
+    fn harness(&self) -> Option<&str> {
+        None
+    }
+
+    // How the compiler's errors are laid out, to turn them into diagnostics
+    fn diagnostics_format(&self) -> DiagnosticsFormat {
+        DiagnosticsFormat::Gcc
+    }
+
+    // Compile and run commands as listed by `GET /languages`
+    fn compile_command_template(&self) -> Option<String>;
+
+    fn run_command_template(&self) -> String;
+}
+
+// Source files of a submission, relative to its folder
+#[derive(Clone)]
+pub struct Sources {
+    pub entry_point: String,
+    pub files: Vec<String>,
+}
+
+// A language described by command templates, which covers everything we
+// support so far. The templates may use
+//
+// - `{file}` for the entry point, e.g. `src/main.c`
+// - `{stem}` for its file name without extension, e.g. `main`
+// - `{dir}` for the folder it is in, e.g. `src`, or `.` at the top
+// - `{module}` for its path without extension and with dots instead of
+//   slashes, e.g. `com.acme.Main` for `com/acme/Main.java`
+// - `{files}` for all source files with the same extension as the entry
+//   point, as separate arguments
+//
+// `source_file` may use `{class}`, the name captured by `class_pattern` from
+// the source code, for languages where the file has to be named after the
+// main class.
+//
+// `harness` names the driver used for questions with a harness, languages
+// without one only take whole programs. `diagnostics` is the format of the
+// compiler's errors.
+#[derive(Clone)]
+pub struct Toolchain {
+    pub name: String,
+    pub language_version: Option<String>,
+    pub aliases: Vec<String>,
+    pub source_file: String,
+    pub class_pattern: Option<Regex>,
+    pub compile: Option<String>,
+    pub run: String,
+    pub version: Option<String>,
+    pub time_multiplier: f64,
+    pub harness: Option<String>,
+    pub diagnostics: DiagnosticsFormat,
+}
+
+fn expand(template: &str, sources: &Sources) -> Result<Vec<String>, CodingError> {
+    let entry_point = Path::new(&sources.entry_point);
+    let stem = match entry_point.file_stem().and_then(|stem| stem.to_str()) {
+        Some(stem) => stem,
+        None => return Err(CodingError::FileNameError),
+    };
+    let module = entry_point
+        .with_extension("")
+        .to_string_lossy()
+        .replace('/', ".");
+    let dir = match entry_point.parent().and_then(|dir| dir.to_str()) {
+        Some("") | None => ".",
+        Some(dir) => dir,
+    };
+    let extension = entry_point.extension();
+    let mut argv = Vec::new();
+    for arg in template.split_whitespace() {
+        if arg == "{files}" {
+            argv.extend(
+                sources
+                    .files
+                    .iter()
+                    .filter(|file| Path::new(file).extension() == extension)
+                    .cloned(),
+            );
+            continue;
+        }
+        argv.push(
+            arg.replace("{file}", &sources.entry_point)
+                .replace("{stem}", stem)
+                .replace("{dir}", dir)
+                .replace("{module}", &module),
+        );
+    }
+    Ok(argv)
+}
+
+impl Language for Toolchain {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn language_version(&self) -> Option<&str> {
+        self.language_version.as_deref()
+    }
+
+    fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    fn source_file_name(&self, source_code: &str) -> Result<String, CodingError> {
+        let class_pattern = match &self.class_pattern {
+            Some(class_pattern) => class_pattern,
+            None => return Ok(self.source_file.clone()),
+        };
+        match class_pattern
+            .captures(source_code)
+            .and_then(|captures| captures.get(1))
+        {
+            Some(class_name) => Ok(self.source_file.replace("{class}", class_name.as_str())),
+            None => Err(CodingError::InvalidPublicClass),
+        }
+    }
+
+    fn is_compiled(&self) -> bool {
+        self.compile.is_some()
+    }
+
+    fn compile_command(&self, sources: &Sources) -> Result<Vec<String>, CodingError> {
+        match &self.compile {
+            Some(compile) => expand(compile, sources),
+            None => Err(CodingError::FileError),
+        }
+    }
+
+    fn run_command(&self, sources: &Sources) -> Result<Vec<String>, CodingError> {
+        expand(&self.run, sources)
+    }
+
+    fn time_multiplier(&self) -> f64 {
+        self.time_multiplier
+    }
+
+    fn version_command(&self) -> Option<Vec<String>> {
+        self.version
+            .as_ref()
+            .map(|version| version.split_whitespace().map(String::from).collect())
+    }
+
+    fn harness(&self) -> Option<&str> {
+        self.harness.as_deref()
+    }
+
+    fn diagnostics_format(&self) -> DiagnosticsFormat {
+        self.diagnostics
+    }
+
+    fn compile_command_template(&self) -> Option<String> {
+        self.compile.clone()
+    }
+
+    fn run_command_template(&self) -> String {
+        self.run.clone()
+    }
+}
+
+fn toolchain(
+    name: &str,
+    source_file: &str,
+    compile: Option<&str>,
+    run: &str,
+    version: &str,
+) -> Toolchain {
+    Toolchain {
+        name: name.to_string(),
+        language_version: None,
+        aliases: Vec::new(),
+        source_file: source_file.to_string(),
+        class_pattern: None,
+        compile: compile.map(String::from),
+        run: run.to_string(),
+        version: Some(version.to_string()),
+        time_multiplier: 1.0,
+        harness: None,
+        diagnostics: DiagnosticsFormat::Gcc,
+    }
+}
+
+fn builtin_toolchains() -> Vec<Toolchain> {
+    vec![
+        Toolchain {
+            class_pattern: Some(Regex::new(r"public\s+class\s+(\w+)\s*\{").unwrap()),
+            harness: Some(String::from("java")),
+            diagnostics: DiagnosticsFormat::Javac,
+            ..toolchain(
+                "java",
+                "{class}.java",
+                Some("javac {files}"),
+                "java -cp . {module}",
+                "javac -version",
+            )
+        },
+        toolchain(
+            "c",
+            "main.c",
+            Some("gcc {files} -o {stem}"),
+            "./{stem}",
+            "gcc --version",
+        ),
+        Toolchain {
+            harness: Some(String::from("cpp")),
+            ..toolchain(
+                "cpp",
+                "main.cpp",
+                Some("g++ {files} -o {stem}"),
+                "./{stem}",
+                "g++ --version",
+            )
+        },
+        Toolchain {
+            aliases: vec![String::from("c#")],
+            diagnostics: DiagnosticsFormat::Mcs,
+            ..toolchain(
+                "csharp",
+                "main.cs",
+                Some("mcs {files} -out:{stem}.exe"),
+                "mono {stem}.exe",
+                "mcs --version",
+            )
+        },
+        // Builds the entry point's whole package, inside its module when
+        // there is a go.mod and on its own otherwise
+        toolchain(
+            "go",
+            "main.go",
+            Some("env GO111MODULE=auto go build -o {stem} ./{dir}"),
+            "./{stem}",
+            "go version",
+        ),
+        Toolchain {
+            harness: Some(String::from("javascript")),
+            ..toolchain(
+                "javascript",
+                "index.js",
+                None,
+                "node {file}",
+                "node --version",
+            )
+        },
+        toolchain("julia", "main.jl", None, "julia {file}", "julia --version"),
+        toolchain(
+            "kotlin",
+            "main.kt",
+            Some("kotlinc {files} -include-runtime -d {stem}.jar"),
+            "java -jar {stem}.jar",
+            "kotlinc -version",
+        ),
+        Toolchain {
+            harness: Some(String::from("python")),
+            ..toolchain(
+                "python",
+                "main.py",
+                None,
+                "python3 {file}",
+                "python3 --version",
+            )
+        },
+        toolchain("ruby", "main.rb", None, "ruby {file}", "ruby --version"),
+        Toolchain {
+            diagnostics: DiagnosticsFormat::Rustc,
+            ..toolchain(
+                "rust",
+                "main.rs",
+                Some("rustc {file} -o {stem}"),
+                "./{stem}",
+                "rustc --version",
+            )
+        },
+        Toolchain {
+            class_pattern: Some(Regex::new(r"object\s+(\w+)\s*\{").unwrap()),
+            diagnostics: DiagnosticsFormat::Javac,
+            ..toolchain(
+                "scala",
+                "{class}.scala",
+                Some("scalac {files}"),
+                "scala -cp . {module}",
+                "scalac -version",
+            )
+        },
+        toolchain(
+            "swift",
+            "main.swift",
+            Some("swiftc {files} -o {stem}"),
+            "./{stem}",
+            "swiftc --version",
+        ),
+        toolchain(
+            "typescript",
+            "index.ts",
+            Some("tsc {files}"),
+            "node {dir}/{stem}.js",
+            "tsc --version",
+        ),
+        toolchain(
+            "zig",
+            "main.zig",
+            Some("zig build-exe {file}"),
+            "./{stem}",
+            "zig version",
+        ),
+    ]
+}
+
+// Entries of a language configuration file, see `Languages::load`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToolchainEntry {
+    aliases: Option<Vec<String>>,
+    source_file: Option<String>,
+    class_pattern: Option<String>,
+    compile: Option<String>,
+    run: Option<String>,
+    version: Option<String>,
+    time_multiplier: Option<f64>,
+    harness: Option<String>,
+    diagnostics: Option<DiagnosticsFormat>,
+    enabled: Option<bool>,
+    #[serde(default)]
+    versions: BTreeMap<String, ToolchainEntry>,
+}
+
+impl ToolchainEntry {
+    fn apply(self, toolchain: &mut Toolchain) -> Result<(), String> {
+        if let Some(aliases) = self.aliases {
+            toolchain.aliases = aliases;
+        }
+        if let Some(source_file) = self.source_file {
+            toolchain.source_file = source_file;
+        }
+        if let Some(class_pattern) = self.class_pattern {
+            toolchain.class_pattern =
+                Some(Regex::new(&class_pattern).map_err(|err| err.to_string())?);
+        }
+        if let Some(compile) = self.compile {
+            toolchain.compile = Some(compile);
+        }
+        if let Some(run) = self.run {
+            toolchain.run = run;
+        }
+        if let Some(version) = self.version {
+            toolchain.version = Some(version);
+        }
+        if let Some(time_multiplier) = self.time_multiplier {
+            if !(time_multiplier > 0.0 && time_multiplier.is_finite()) {
+                return Err(String::from("time_multiplier must be a positive number"));
+            }
+            toolchain.time_multiplier = time_multiplier;
+        }
+        if let Some(harness) = self.harness {
+            if !DRIVERS.iter().any(|(name, _)| *name == harness) {
+                return Err(format!("there is no `{}` harness", harness));
+            }
+            toolchain.harness = Some(harness);
+        }
+        if let Some(diagnostics) = self.diagnostics {
+            toolchain.diagnostics = diagnostics;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LanguagesFile {
+    #[serde(default)]
+    languages: BTreeMap<String, ToolchainEntry>,
+}
+
+// What `Languages::detect` found out about a toolchain
+#[derive(Clone)]
+struct Detected {
+    available: bool,
+    version: Option<String>,
+}
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Runs a version command like a submission, sandbox included, and returns
+// the first line it printed. Some tools print their version on stderr.
+async fn probe(argv: &[String], workdir: &Path, config: &Config) -> Detected {
+    let unavailable = Detected {
+        available: false,
+        version: None,
+    };
+    let (program, args) = match argv.split_first() {
+        Some(split) => split,
+        None => return unavailable,
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    let limits = config.limits(PROBE_TIMEOUT, None);
+    let output = match runner::run(command, workdir, "", &limits, config).await {
+        Ok(output) if output.exit_code == Some(0) && !output.timed_out => output,
+        _ => return unavailable,
+    };
+    let version = [&output.stdout, &output.stderr].iter().find_map(|stream| {
+        stream
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(String::from)
+    });
+    Detected {
+        available: true,
+        version,
+    }
+}
+
+// Name of the language as shown in errors and logs, with its version
+pub fn label(language: &dyn Language) -> String {
+    match language.language_version() {
+        Some(version) => format!("{} {}", language.name(), version),
+        None => language.name().to_string(),
+    }
+}
+
+#[derive(Clone)]
+struct Registered {
+    language: Arc<dyn Language>,
+    detected: Option<Detected>,
+}
+
+// Languages by name and version, including their aliases
+#[derive(Clone, Default)]
+pub struct Languages {
+    registered: Vec<Registered>,
+}
+
+impl Languages {
+    pub fn builtin() -> Languages {
+        let mut languages = Languages::default();
+        for toolchain in builtin_toolchains() {
+            languages.register(toolchain);
+        }
+        languages
+    }
+
+    // Built-in languages with the changes from a language configuration
+    // file applied
+    pub fn load(path: &Path) -> Result<Languages, CodingError> {
+        let invalid = |reason: String| CodingError::InvalidConfig {
+            reason: format!("{} :: {}", path.display(), reason),
+        };
+        let contents = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        Languages::from_toml(&contents).map_err(invalid)
+    }
+
+    // Entries named after a built-in language override only the fields they
+    // set, other entries define a new language. Versions of a language start
+    // out as a copy of it, and go along with it when it is disabled.
+    fn from_toml(contents: &str) -> Result<Languages, String> {
+        let file: LanguagesFile = toml::from_str(contents).map_err(|err| err.to_string())?;
+
+        let mut toolchains = builtin_toolchains();
+        let mut versions = Vec::new();
+        for (name, mut entry) in file.languages {
+            let index = match toolchains
+                .iter()
+                .position(|toolchain| toolchain.name == name)
+            {
+                Some(index) => index,
+                None => {
+                    let (source_file, run) = match (&entry.source_file, &entry.run) {
+                        (Some(source_file), Some(run)) => (source_file, run),
+                        _ => {
+                            return Err(format!(
+                                "`{}` is not a built-in language and needs a `source_file` and a `run` command",
+                                name
+                            ))
+                        }
+                    };
+                    toolchains.push(Toolchain {
+                        version: None,
+                        ..toolchain(&name, source_file, None, run, "")
+                    });
+                    toolchains.len() - 1
+                }
+            };
+            let enabled = entry.enabled != Some(false);
+            let language_versions = std::mem::take(&mut entry.versions);
+            entry
+                .apply(&mut toolchains[index])
+                .map_err(|reason| format!("`{}` :: {}", name, reason))?;
+            if !enabled {
+                toolchains.remove(index);
+                continue;
+            }
+            for (language_version, version_entry) in language_versions {
+                if !version_entry.versions.is_empty() {
+                    return Err(format!(
+                        "`{} {}` :: versions can't have versions",
+                        name, language_version
+                    ));
+                }
+                if version_entry.enabled == Some(false) {
+                    continue;
+                }
+                let mut toolchain = Toolchain {
+                    language_version: Some(language_version.clone()),
+                    ..toolchains[index].clone()
+                };
+                version_entry
+                    .apply(&mut toolchain)
+                    .map_err(|reason| format!("`{} {}` :: {}", name, language_version, reason))?;
+                versions.push(toolchain);
+            }
+        }
+
+        let mut languages = Languages::default();
+        for toolchain in toolchains.into_iter().chain(versions) {
+            languages.register(toolchain);
+        }
+        Ok(languages)
+    }
+
+    // A language registered under a name and version already in use
+    // replaces the previous one
+    pub fn register(&mut self, language: impl Language + 'static) {
+        self.registered.retain(|registered| {
+            registered.language.name() != language.name()
+                || registered.language.language_version() != language.language_version()
+        });
+        self.registered.push(Registered {
+            language: Arc::new(language),
+            detected: None,
+        });
+    }
+
+    // Without a version, the language's default toolchain
+    pub fn get(&self, name: &str, version: Option<&str>) -> Option<Arc<dyn Language>> {
+        self.registered
+            .iter()
+            .map(|registered| &registered.language)
+            .find(|language| {
+                (language.name() == name || language.aliases().iter().any(|alias| alias == name))
+                    && language.language_version() == version
+            })
+            .cloned()
+    }
+
+    // Runs the version command of every language in the configured sandbox,
+    // so that toolchains it doesn't give access to count as unavailable. All
+    // at once, as some of them take a while to start.
+    pub async fn detect(&mut self, config: &Config) {
+        let workdir = Path::new("tmp").join(format!("probe-{}", Uuid::new_v4()));
+        if let Err(err) = tokio::fs::create_dir_all(&workdir).await {
+            error!("Unable to create {} :: {}", workdir.display(), err);
+            return;
+        }
+        let workdir = workdir.as_path();
+        let probes = self.registered.iter_mut().filter_map(|registered| {
+            let argv = registered.language.version_command()?;
+            Some(async move {
+                registered.detected = Some(probe(&argv, workdir, config).await);
+            })
+        });
+        join_all(probes).await;
+        if let Err(err) = tokio::fs::remove_dir_all(workdir).await {
+            error!("Unable to remove {} :: {}", workdir.display(), err);
+        }
+        for registered in &self.registered {
+            if !Self::available(registered) {
+                warn!(
+                    "No working toolchain found for {}, disabling it",
+                    label(registered.language.as_ref())
+                );
+            }
+        }
+    }
+
+    // Languages without a version command are assumed to be there
+    fn available(registered: &Registered) -> bool {
+        registered
+            .detected
+            .as_ref()
+            .is_none_or(|detected| detected.available)
+    }
+
+    pub fn is_available(&self, language: &dyn Language) -> bool {
+        self.registered
+            .iter()
+            .find(|registered| {
+                registered.language.name() == language.name()
+                    && registered.language.language_version() == language.language_version()
+            })
+            .is_some_and(Self::available)
+    }
+
+    pub fn list(&self) -> Vec<LanguageInfo> {
+        self.registered
+            .iter()
+            .map(|registered| {
+                let language = &registered.language;
+                LanguageInfo {
+                    name: language.name().to_string(),
+                    language_version: language.language_version().map(String::from),
+                    aliases: language.aliases().to_vec(),
+                    compiled: language.is_compiled(),
+                    available: Self::available(registered),
+                    version: registered
+                        .detected
+                        .as_ref()
+                        .and_then(|detected| detected.version.clone()),
+                    harness: language.harness().map(String::from),
+                    compile_command: language.compile_command_template(),
+                    run_command: language.run_command_template(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Languages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.registered
+                    .iter()
+                    .map(|registered| label(registered.language.as_ref())),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn load(contents: &str) -> Languages {
+        Languages::from_toml(contents).unwrap()
+    }
+
+    fn templates(
+        languages: &Languages,
+        name: &str,
+        version: Option<&str>,
+    ) -> (Option<String>, String) {
+        let language = languages.get(name, version).unwrap();
+        (
+            language.compile_command_template(),
+            language.run_command_template(),
+        )
+    }
+
+    #[test]
+    fn builtin_languages_only_change_where_set() {
+        let builtin = Languages::builtin();
+        let languages = load(
+            r#"
+            [languages.cpp]
+            compile = "clang++ {files} -o {stem}"
+            time_multiplier = 1.5
+            "#,
+        );
+        let cpp = languages.get("cpp", None).unwrap();
+        assert_eq!(
+            cpp.compile_command_template().as_deref(),
+            Some("clang++ {files} -o {stem}")
+        );
+        assert_eq!(
+            cpp.run_command_template(),
+            templates(&builtin, "cpp", None).1
+        );
+        assert_eq!(cpp.time_multiplier(), 1.5);
+        assert_eq!(
+            templates(&languages, "python", None),
+            templates(&builtin, "python", None)
+        );
+    }
+
+    #[test]
+    fn new_languages_need_a_source_file_and_a_run_command() {
+        let languages = load(
+            r#"
+            [languages.pypy]
+            source_file = "main.py"
+            run = "pypy3 {file}"
+            aliases = ["pypy3"]
+            "#,
+        );
+        let pypy = languages.get("pypy3", None).unwrap();
+        assert_eq!(pypy.name(), "pypy");
+        assert!(!pypy.is_compiled());
+        assert_eq!(
+            Languages::from_toml("[languages.pypy]\nrun = \"pypy3 {file}\"").err(),
+            Some(String::from(
+                "`pypy` is not a built-in language and needs a `source_file` and a `run` command"
+            ))
+        );
+    }
+
+    #[test]
+    fn versions_start_out_as_a_copy() {
+        let languages = load(
+            r#"
+            [languages.python]
+            time_multiplier = 2.0
+
+            [languages.python.versions."3.8"]
+            run = "python3.8 {file}"
+
+            [languages.python.versions."3.12"]
+            enabled = false
+            "#,
+        );
+        let default = languages.get("python", None).unwrap();
+        let old = languages.get("python", Some("3.8")).unwrap();
+        assert_eq!(old.language_version(), Some("3.8"));
+        assert_eq!(old.run_command_template(), "python3.8 {file}");
+        assert_eq!(old.time_multiplier(), 2.0);
+        assert_eq!(old.aliases(), default.aliases());
+        assert_ne!(old.run_command_template(), default.run_command_template());
+        assert!(languages.get("python", Some("3.12")).is_none());
+    }
+
+    #[test]
+    fn disabled_languages_take_their_versions_along() {
+        let languages = load(
+            r#"
+            [languages.python]
+            enabled = false
+
+            [languages.python.versions."3.8"]
+            run = "python3.8 {file}"
+            "#,
+        );
+        assert!(languages.get("python", None).is_none());
+        assert!(languages.get("python", Some("3.8")).is_none());
+        assert!(languages.get("cpp", None).is_some());
+    }
+
+    #[actix_web::test]
+    async fn detects_toolchains_the_way_submissions_run() {
+        let mut languages = load(
+            r#"
+            [languages.here]
+            source_file = "main.sh"
+            run = "sh {file}"
+            version = "echo here 1.0"
+
+            [languages.gone]
+            source_file = "main.sh"
+            run = "gone {file}"
+            version = "/nonexistent/gone --version"
+
+            [languages.broken]
+            source_file = "main.sh"
+            run = "sh {file}"
+            version = "false"
+            "#,
+        );
+        languages.detect(&testing::config()).await;
+        let info = |name: &str| {
+            languages
+                .list()
+                .into_iter()
+                .find(|info| info.name == name)
+                .unwrap()
+        };
+        assert!(info("here").available);
+        assert_eq!(info("here").version.as_deref(), Some("here 1.0"));
+        for name in ["gone", "broken"] {
+            let language = languages.get(name, None).unwrap();
+            assert!(!languages.is_available(language.as_ref()));
+            assert_eq!(info(name).version, None);
+        }
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        let nested = r#"
+            [languages.cpp.versions."17"]
+            versions = { "x" = {} }
+            "#;
+        assert_eq!(
+            Languages::from_toml(nested).err(),
+            Some(String::from("`cpp 17` :: versions can't have versions"))
+        );
+        let multiplier = "[languages.cpp.versions.\"17\"]\ntime_multiplier = 0.0";
+        assert_eq!(
+            Languages::from_toml(multiplier).err(),
+            Some(String::from(
+                "`cpp 17` :: time_multiplier must be a positive number"
+            ))
+        );
+        assert!(Languages::from_toml("[languages.cpp]\ncompiler = \"g++\"").is_err());
+    }
+}
@@ -1,434 +1,722 @@
 #![allow(clippy::needless_return)]
 
-use actix_web::{HttpResponse, Responder};
+use checker::{Checker, Judgement};
+use config::Config;
+use language::{Language, Sources};
 use log::{debug, error};
-use regex::Regex;
+use problem::Problem;
+use runner::ProcessOutput;
+use special_judge::SpecialJudge;
 use std::{
-    path::Path,
-    time::{Duration, Instant},
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use types::{
+    CodingError, Limits, Question, RunResult, Submission, SubmitResult, TestcaseResult, Verdict,
 };
-use tokio::{io::AsyncReadExt, process::Command};
-use types::{CodingError, ErrorResponse, Question};
 use uuid::Uuid;
 // Library to spawn process in parallel and execute
 // source code in various languages.
 // Author: @rohitp934
 // License: MIT
 // Version: 0.1.0
+mod archive;
+pub mod backend;
+mod cgroup;
+pub mod checker;
+pub mod config;
+pub mod diagnostics;
+mod diff;
+pub mod harness;
+pub mod language;
+pub mod problem;
+mod runner;
+mod sandbox;
+mod seccomp;
+mod special_judge;
+pub mod subtask;
+#[cfg(test)]
+mod testing;
 pub mod types;
 
+// Compilers run under the same runner as submissions, with a fixed time limit
+const COMPILE_TIME_LIMIT: Duration = Duration::from_secs(30);
+// Longest `timeout` a question can ask for, in seconds
+const MAX_TIMEOUT: i32 = 60;
+
 struct Program {
-    file_name: String,
-    binary_name: Option<String>,
-    language: String,
+    sources: Sources,
+    folder_name: String,
+    language: Arc<dyn Language>,
     time_limit: i32,
-    is_compiled: bool,
+    memory_limit_mb: Option<u64>,
 }
 
 struct CompiledProgram {
-    src_file_path: String,
-    language: String,
-    file_name_without_ext: String,
+    folder_name: String,
+    sources: Sources,
+    language: Arc<dyn Language>,
 }
 
-fn make_filename(language: &str, src: &str) -> Result<String, CodingError> {
-    match language {
-        "java" => {
-            let re = Regex::new(r"public\s+class\s+(\w+)\s*\{").unwrap();
-            if let Some(captures) = re.captures(src) {
-                let class_name = captures.get(1).unwrap().as_str();
-                Ok(format!("{}.java", class_name))
-            } else {
-                Err(CodingError::InvalidPublicClass)
-            }
-        }
-        "c" => Ok(String::from("main.c")),
-        "cpp" => Ok(String::from("main.cpp")),
-        "csharp" => Ok(String::from("main.cs")),
-        "go" => Ok(String::from("main.go")),
-        "javascript" => Ok(String::from("index.js")),
-        "julia" => Ok(String::from("main.jl")),
-        "kotlin" => Ok(String::from("main.kt")),
-        "python" => Ok(String::from("main.py")),
-        "ruby" => Ok(String::from("main.rb")),
-        "rust" => Ok(String::from("main.rs")),
-        "scala" => {
-            let re = Regex::new(r"object\s+(\w+)\s*\{").unwrap();
-            if let Some(captures) = re.captures(src) {
-                let class_name = captures.get(1).unwrap().as_str();
-                Ok(format!("{}.scala", class_name))
-            } else {
-                Err(CodingError::InvalidPublicClass)
-            }
+// Normalises a submitted file path, making sure it stays inside the
+// submission folder
+fn relative_path(path: &str) -> Result<String, CodingError> {
+    let invalid = || CodingError::InvalidSubmission {
+        reason: format!("`{}` is not a valid file path", path),
+    };
+    let mut relative = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            // `..`, absolute paths and Windows prefixes
+            _ => return Err(invalid()),
         }
-        "swift" => Ok(String::from("main.swift")),
-        "typescript" => Ok(String::from("index.ts")),
-        "zig" => Ok(String::from("main.zig")),
-        _ => Ok(String::from("")),
+    }
+    match relative.to_str() {
+        Some(relative) if !relative.is_empty() => Ok(relative.to_string()),
+        _ => Err(invalid()),
     }
 }
 
-fn get_file_name_without_ext(file_name: &str) -> Result<&str, CodingError> {
-    let file_stem = Path::new(file_name).file_stem();
-    match file_stem {
-        Some(no_ext) => {
-            let no_ext = no_ext.to_str().unwrap();
-            Ok(no_ext)
+// Paths and contents of the files to write
+type Files = Vec<(String, Vec<u8>)>;
+
+// Makes sure the normalised paths of the files can all be written: none of
+// them twice, and none inside another one
+fn check_paths(files: &Files) -> Result<(), CodingError> {
+    let mut paths = HashSet::with_capacity(files.len());
+    for (path, _) in files {
+        if !paths.insert(path.as_str()) {
+            return Err(CodingError::InvalidSubmission {
+                reason: format!("`{}` is submitted more than once", path),
+            });
+        }
+    }
+    for (path, _) in files {
+        let file = Path::new(path)
+            .ancestors()
+            .skip(1)
+            .filter_map(Path::to_str)
+            .find(|folder| paths.contains(folder));
+        if let Some(file) = file {
+            return Err(CodingError::InvalidSubmission {
+                reason: format!("`{}` can't be in `{}`, which is a file", path, file),
+            });
         }
-        None => Err(CodingError::FileNameError),
     }
+    Ok(())
 }
 
-fn is_compiled_language(language: &str) -> bool {
-    let compiled_langs = vec!["java", "cpp", "c", "rust", "csharp"];
-    if compiled_langs.contains(&language) {
-        return true;
+// Lays out the submission: either the files it was given or unpacked from
+// its archive, or its source code alone in the file the language expects.
+async fn source_files(
+    question: &Question,
+    language: &dyn Language,
+    config: &Config,
+) -> Result<(Sources, Files), CodingError> {
+    let files = match &question.archive {
+        // Decompressing takes a while, it is kept off the async workers
+        Some(archive) => {
+            let archive = archive.clone();
+            let (size_limit, entry_limit) =
+                (config.archive_size_limit_bytes, config.archive_entry_limit);
+            match tokio::task::spawn_blocking(move || {
+                archive::extract(&archive, size_limit, entry_limit)
+            })
+            .await
+            {
+                Ok(files) => files?,
+                Err(err) => {
+                    error!("Unable to extract the archive :: {}", err);
+                    return Err(CodingError::FileError);
+                }
+            }
+        }
+        None => {
+            let mut files = Vec::with_capacity(question.files.len());
+            for file in &question.files {
+                files.push((
+                    relative_path(&file.path)?,
+                    file.content.clone().into_bytes(),
+                ));
+            }
+            check_paths(&files)?;
+            files
+        }
+    };
+    if question.harness.is_some() && !files.is_empty() {
+        return Err(CodingError::InvalidSubmission {
+            reason: String::from("Questions with a harness take a single `source_code`"),
+        });
+    }
+    if files.is_empty() {
+        let source_code = match &question.harness {
+            Some(harness) => harness.wrap(language, &question.source_code)?,
+            None => question.source_code.clone(),
+        };
+        let file_name = language.source_file_name(&source_code)?;
+        let sources = Sources {
+            entry_point: file_name.clone(),
+            files: vec![file_name.clone()],
+        };
+        return Ok((sources, vec![(file_name, source_code.into_bytes())]));
+    }
+    let entry_point = match &question.entry_point {
+        Some(entry_point) => relative_path(entry_point)?,
+        None => {
+            return Err(CodingError::InvalidSubmission {
+                reason: String::from("Submissions with several files need an entry_point"),
+            })
+        }
+    };
+    if !files.iter().any(|(path, _)| *path == entry_point) {
+        return Err(CodingError::InvalidSubmission {
+            reason: format!("The entry point `{}` is not one of the files", entry_point),
+        });
     }
-    false
+    let sources = Sources {
+        entry_point,
+        files: files.iter().map(|(path, _)| path.clone()).collect(),
+    };
+    Ok((sources, files))
 }
 
-async fn init(question: &Question) -> Result<(String, String), CodingError> {
-    let file_name = make_filename(&question.language, &question.source_code)?;
-    let folder_name = format!("{}{}", question.language, Uuid::new_v4());
+// Creates `tmp/{folder_name}` with the given files in it
+async fn write_files(folder_name: &str, files: Files) -> Result<(), CodingError> {
     if let Err(err) = tokio::fs::create_dir_all(format!("tmp/{}", folder_name)).await {
         error!(
             "Something went wrong when trying to create the subdirectories :: {}",
-            err.to_string()
+            err
         );
         return Err(CodingError::FileCreationError);
     };
-    let src_file_path = format!("tmp/{}/{}", folder_name, file_name);
-    if let Err(err) = tokio::fs::write(&src_file_path, &question.source_code).await {
-        error!(
-            "Something went wrong when trying to create the source file. {}",
-            err.to_string()
-        );
-        return Err(CodingError::FileCreationError);
+    for (path, content) in files {
+        let src_file_path = Path::new("tmp").join(folder_name).join(path);
+        if let Some(parent) = src_file_path.parent() {
+            if let Err(err) = tokio::fs::create_dir_all(parent).await {
+                error!(
+                    "Something went wrong when trying to create the subdirectories :: {}",
+                    err
+                );
+                return Err(CodingError::FileCreationError);
+            }
+        }
+        if let Err(err) = tokio::fs::write(&src_file_path, content).await {
+            error!(
+                "Something went wrong when trying to create the source file. {}",
+                err
+            );
+            return Err(CodingError::FileCreationError);
+        }
+    }
+    Ok(())
+}
+
+async fn init(
+    question: &Question,
+    language: &dyn Language,
+    config: &Config,
+) -> Result<(Sources, String), CodingError> {
+    let (sources, files) = source_files(question, language, config).await?;
+    let folder_name = format!("{}{}", language.name(), Uuid::new_v4());
+    write_files(&folder_name, files).await?;
+    debug!("Source files created successfully.");
+    Ok((sources, folder_name))
+}
+
+fn command(argv: &[String]) -> Result<std::process::Command, CodingError> {
+    match argv.split_first() {
+        Some((program, args)) => {
+            let mut command = std::process::Command::new(program);
+            command.args(args);
+            Ok(command)
+        }
+        None => Err(CodingError::FileError),
     }
-    debug!("Source file created successfully.");
-    Ok((file_name, folder_name))
 }
 
 impl CompiledProgram {
-    async fn compile(&self) -> Result<(), CodingError> {
+    async fn compile(&self, config: &Config) -> Result<String, CodingError> {
         // Check if files are present
-        if !Path::new(&self.src_file_path).exists() {
+        let workdir = format!("tmp/{}", self.folder_name);
+        if !Path::new(&workdir).join(&self.sources.entry_point).exists() {
             return Err(CodingError::FileError);
         }
 
-        // Check if language is supported
-        let cmd: String;
-        let args: String;
-
-        match self.language.as_str() {
-            "java" => {
-                cmd = String::from("javac");
-                args = self.src_file_path.to_string();
-            }
-            "c" => {
-                cmd = String::from("gcc");
-                args = format!("{} -o {}", self.src_file_path, self.file_name_without_ext);
-            }
-            "cpp" => {
-                cmd = String::from("g++");
-                args = format!("{} -o {}", self.src_file_path, self.file_name_without_ext);
-            }
-            "c#" => {
-                cmd = String::from("mcs");
-                args = self.src_file_path.to_string();
-            }
-            "kotlin" => {
-                cmd = String::from("kotlinc");
-                args = format!(
-                    "{} -include-runtime -d {}.jar",
-                    self.src_file_path, self.file_name_without_ext
-                );
-            }
-            "rust" => {
-                cmd = String::from("rustc");
-                args = format!("{} -o {}", self.src_file_path, self.file_name_without_ext);
-            }
-            "scala" => {
-                cmd = String::from("scalac");
-                args = self.src_file_path.to_string();
-            }
-            "swift" => {
-                cmd = String::from("swiftc");
-                args = self.src_file_path.to_string();
-            }
-            "typescript" => {
-                cmd = String::from("npx tsc");
-                args = self.src_file_path.to_string();
-            }
-            "zig" => {
-                cmd = String::from("zig");
-                args = format!("build-exe {}", self.src_file_path);
-            }
-            _ => {
-                return Err(CodingError::FileError);
-            }
+        let command = command(&self.language.compile_command(&self.sources)?)?;
+        let limits = config.limits(COMPILE_TIME_LIMIT, None);
+        let output = runner::run(command, Path::new(&workdir), "", &limits, config).await?;
+        if output.timed_out {
+            return Err(CodingError::CompileError {
+                stderr: String::from("Compilation timed out."),
+                diagnostics: Vec::new(),
+            });
         }
-
-        let child = Command::new(&cmd)
-            .args(args.split_whitespace())
-            .output()
-            .await;
-        match child {
-            Err(_) => Err(CodingError::ProcessError),
-            Ok(output) => {
-                // Get the stderr output as a &str
-                if let Ok(stderr) = String::from_utf8(output.stderr) {
-                    // Check for errors
-                    debug!("{}", stderr);
-                    if output.status.code() != Some(0) {
-                        Err(CodingError::CompileError)
-                    } else {
-                        Ok(())
-                    }
-                } else {
-                    Err(CodingError::InvalidStringFromConsole)
-                }
-            }
+        // Check for errors
+        if output.exit_code != Some(0) {
+            Err(CodingError::CompileError {
+                diagnostics: diagnostics::parse(self.language.diagnostics_format(), &output.stderr),
+                stderr: output.stderr,
+            })
+        } else {
+            // Warnings are still worth showing to the candidate
+            Ok(output.stderr)
         }
     }
 }
 
+// `memory_limit_mb` in bytes, as long as it is within what the server allows
+fn memory_limit_bytes(mb: u64, config: &Config) -> Result<u64, CodingError> {
+    match mb.checked_mul(1024 * 1024) {
+        Some(bytes) if mb > 0 && mb <= config.max_memory_limit_mb => Ok(bytes),
+        _ => Err(CodingError::InvalidSubmission {
+            reason: format!(
+                "`memory_limit_mb` has to be between 1 and {} MB, got {}",
+                config.max_memory_limit_mb, mb
+            ),
+        }),
+    }
+}
+
 impl Program {
-    async fn run(&self) -> Result<(), CodingError> {
-        // Create the run command based on the language
-        let cmd: String;
-        let args: String;
+    fn limits(&self, config: &Config) -> Result<Limits, CodingError> {
+        let time = Duration::try_from_secs_f64(
+            f64::from(self.time_limit) * self.language.time_multiplier(),
+        )
+        .map_err(|err| CodingError::InvalidSubmission {
+            reason: format!("Invalid time limit :: {}", err),
+        })?;
+        let memory_bytes = match self.memory_limit_mb {
+            Some(mb) => Some(memory_limit_bytes(mb, config)?),
+            None => None,
+        };
+        Ok(config.limits(time, memory_bytes))
+    }
 
-        if self.is_compiled {
-            let binary = self.binary_name.as_ref().unwrap();
+    async fn run(&self, input: &str, config: &Config) -> Result<ProcessOutput, CodingError> {
+        let command = command(&self.language.run_command(&self.sources)?)?;
+        let workdir = format!("tmp/{}", self.folder_name);
+        runner::run(
+            command,
+            Path::new(&workdir),
+            input,
+            &self.limits(config)?,
+            config,
+        )
+        .await
+    }
 
-            // Create the run command based on the language
-            // specifically for compiled languages
-            match self.language.as_str() {
-                "java" => {
-                    cmd = String::from("java");
-                    args = format!("{}.class", binary);
-                }
-                "c" => {
-                    cmd = format!("./{}", binary);
-                    args = String::new();
-                }
-                "cpp" => {
-                    cmd = format!("./{}", binary);
-                    args = String::new();
-                }
-                "c#" => {
-                    cmd = String::from("mono");
-                    args = format!("{}.exe", binary);
-                }
-                "kotlin" => {
-                    cmd = String::from("java");
-                    args = format!("-jar {}.jar", binary);
-                }
-                "rust" => {
-                    cmd = format!("./{}", binary);
-                    args = String::new();
-                }
-                "scala" => {
-                    cmd = String::from("scala");
-                    args = format!("{}.class", binary);
-                }
-                "swift" => {
-                    cmd = String::from("swift");
-                    args = binary.to_string();
-                }
-                "typescript" => {
-                    cmd = String::from("node");
-                    args = format!("{}.js", binary);
-                }
-                "zig" => {
-                    cmd = format!("./{}", binary);
-                    args = String::new();
-                }
-                _ => {
-                    return Err(CodingError::FileError);
-                }
-            }
-            let mut stdout = String::new();
-            let mut stderr = String::new();
+    // Runs the program against the interactor, both at once and talking to
+    // each other, and returns what the interactor made of it
+    async fn interact(
+        &self,
+        interactor: &SpecialJudge,
+        input: &str,
+        answer: &str,
+        config: &Config,
+    ) -> Result<(ProcessOutput, Result<Judgement, CodingError>), CodingError> {
+        let command = command(&self.language.run_command(&self.sources)?)?;
+        let workdir = format!("tmp/{}", self.folder_name);
+        let limits = self.limits(config)?;
+        let (program_end, interactor_end) = runner::connect()?;
+        let (output, judgement) = tokio::join!(
+            runner::run_connected(command, Path::new(&workdir), program_end, &limits, config),
+            interactor.interact(input, answer, interactor_end, limits.time, config),
+        );
+        Ok((output?, judgement))
+    }
+}
 
-            let mut run_binary = Command::new(&cmd)
-                .args(args.split_whitespace())
-                .spawn()
-                .unwrap();
-            // Set a time limit
-            let timeout = Duration::from_secs(self.time_limit as u64);
-            let start = Instant::now();
-            loop {
-                if start.elapsed() > timeout {
-                    // println!("Time limit exceeded for: {}", self.folder_name);
-                    return Err(CodingError::TimeLimitExceeded);
-                }
-                match run_binary.try_wait() {
-                    Ok(None) => {
-                        // println!("Program still running for: {}", self.folder_name);
-                        continue;
-                    }
-                    Err(_e) => {
-                        // println!("Program failed for: {}", self.folder_name);
-                        run_binary
-                            .stderr
-                            .unwrap()
-                            .read_to_string(&mut stderr)
-                            .await
-                            .unwrap();
-                        return Err(CodingError::InvalidStringFromConsole);
-                    }
-                    Ok(Some(status)) => {
-                        if status.success() {
-                            run_binary
-                                .stdout
-                                .unwrap()
-                                .read_to_string(&mut stdout)
-                                .await
-                                .unwrap();
-                            //? Need to return the stdout
-                            return Ok(());
-                        } else {
-                            run_binary
-                                .stderr
-                                .unwrap()
-                                .read_to_string(&mut stderr)
-                                .await
-                                .unwrap();
-                            return Err(CodingError::InvalidStringFromConsole);
-                        }
-                    }
-                }
-            }
-        } else {
-            // Create the run command based on the language
-            // specifically for interpreted languages
-            match self.language.as_str() {
-                "python" => {
-                    cmd = String::from("python3");
-                    args = self.file_name.to_string();
-                }
-                "ruby" => {
-                    cmd = String::from("ruby");
-                    args = self.file_name.to_string();
-                }
-                "javascript" => {
-                    cmd = String::from("node");
-                    args = self.file_name.to_string();
-                }
-                "go" => {
-                    cmd = String::from("go");
-                    args = format!("run {}", self.file_name);
-                }
-                "julia" => {
-                    cmd = String::from("julia");
-                    args = self.file_name.to_string();
-                }
-                _ => {
-                    return Err(CodingError::FileError);
-                }
-            }
-            let run_binary = Command::new(&cmd)
-                .args(args.split_whitespace())
-                .output()
-                .await;
-            match run_binary {
-                Err(_) => return Err(CodingError::ProcessError),
-                Ok(output) => {
-                    // Get the stderr output as a &str
-                    if let Ok(stderr) = String::from_utf8(output.stderr) {
-                        // Check for errors
-                        debug!("{}", stderr);
-                        if output.status.code() != Some(0) {
-                            return Err(CodingError::CompileError);
-                        } else {
-                            return Ok(());
-                        }
-                    } else {
-                        return Err(CodingError::InvalidStringFromConsole);
-                    }
-                }
-            }
-        }
+// Checkers and interactors failing is the question's fault rather than the
+// submission's, it is reported on the testcase
+fn judge_failure(id: &str, err: CodingError) -> Judgement {
+    error!("Checker Error for id: {}! :: {}", id, err);
+    Judgement {
+        message: Some(err.to_string()),
+        ..Judgement::from(err.verdict())
     }
 }
 
 async fn cleanup(folder_name: &str) -> Result<(), CodingError> {
     match tokio::fs::remove_dir_all(&folder_name).await {
         Err(err) => {
-            error!("Cleanup Error :: {}", err.to_string());
+            error!("Cleanup Error :: {}", err);
             Err(CodingError::CleanupError)
         }
         Ok(()) => Ok(()),
     }
 }
 
-pub async fn execute(question: Question) -> impl Responder {
-    let src = match init(&question).await {
-        Ok(init_response) => init_response,
+fn overall_verdict(testcases: &[TestcaseResult]) -> Verdict {
+    match testcases.iter().find(|t| t.verdict != Verdict::Accepted) {
+        Some(failed) => failed.verdict,
+        None if testcases.is_empty() => Verdict::Ok,
+        None => Verdict::Accepted,
+    }
+}
+
+pub async fn execute(question: Question, config: &Config) -> RunResult {
+    let language = match config
+        .languages
+        .get(&question.language, question.language_version.as_deref())
+    {
+        Some(language) => language,
+        None => {
+            let err = CodingError::UnsupportedLanguage {
+                language: match &question.language_version {
+                    Some(version) => format!("{} {}", question.language, version),
+                    None => question.language.clone(),
+                },
+            };
+            error!("Something went wrong for id: {}!\n{}", &question.id, err);
+            return RunResult::from_error(question.id, err);
+        }
+    };
+    if !config.languages.is_available(language.as_ref()) {
+        let err = CodingError::LanguageUnavailable {
+            language: language::label(language.as_ref()),
+        };
+        error!("Something went wrong for id: {}!\n{}", &question.id, err);
+        return RunResult::from_error(question.id, err);
+    }
+    if question.interactor.is_some() && question.harness.is_some() {
+        let err = CodingError::InvalidSubmission {
+            reason: String::from("Interactive questions can't have a harness"),
+        };
+        error!("Something went wrong for id: {}!\n{}", &question.id, err);
+        return RunResult::from_error(question.id, err);
+    }
+    if question.timeout <= 0 || question.timeout > MAX_TIMEOUT {
+        let err = CodingError::InvalidSubmission {
+            reason: format!(
+                "`timeout` has to be between 1 and {} seconds, got {}",
+                MAX_TIMEOUT, question.timeout
+            ),
+        };
+        error!("Something went wrong for id: {}!\n{}", &question.id, err);
+        return RunResult::from_error(question.id, err);
+    }
+    if let Some(Err(err)) = question
+        .memory_limit_mb
+        .map(|mb| memory_limit_bytes(mb, config))
+    {
+        error!("Something went wrong for id: {}!\n{}", &question.id, err);
+        return RunResult::from_error(question.id, err);
+    }
+    if question.stdin.is_some()
+        && (question.interactor.is_some()
+            || !question.sample_testcases.is_empty()
+            || !question.subtasks.is_empty())
+    {
+        let err = CodingError::InvalidSubmission {
+            reason: String::from(
+                "Custom input can't be given along with testcases, subtasks or an interactor",
+            ),
+        };
+        error!("Something went wrong for id: {}!\n{}", &question.id, err);
+        return RunResult::from_error(question.id, err);
+    }
+    if let Err(err) = subtask::validate(&question.subtasks, question.sample_testcases.len()) {
+        error!("Something went wrong for id: {}!\n{}", &question.id, err);
+        return RunResult::from_error(question.id, err);
+    }
+    // Harness testcases are checked before anything gets compiled. Custom
+    // input becomes a testcase with nothing to compare against.
+    let sample_testcases = match (&question.stdin, &question.harness) {
+        (Some(stdin), Some(harness)) => harness
+            .input(stdin)
+            .map(|input| vec![(input, String::new())]),
+        (Some(stdin), None) => Ok(vec![(stdin.clone(), String::new())]),
+        (None, Some(harness)) => harness.testcases(&question.sample_testcases),
+        (None, None) => Ok(question.sample_testcases.clone()),
+    };
+    let sample_testcases = match sample_testcases {
+        Ok(testcases) => testcases,
         Err(err) => {
             error!("Something went wrong for id: {}!\n{}", &question.id, err);
-            return HttpResponse::BadRequest().json(ErrorResponse {
-                id: question.id,
-                error: err.to_string(),
-            });
+            return RunResult::from_error(question.id, err);
+        }
+    };
+    let interactor = match &question.interactor {
+        Some(program) => match config.special_judges.get(program, config).await {
+            Ok(interactor) => Some(interactor),
+            Err(err) => {
+                error!("Interactor Error for id: {}! :: {}", &question.id, err);
+                return RunResult::from_error(question.id, err);
+            }
+        },
+        None => None,
+    };
+    let special_judge = match &question.checker {
+        // Custom input isn't checked
+        Checker::Program(program) if question.stdin.is_none() => {
+            match config.special_judges.get(program, config).await {
+                Ok(special_judge) => Some(special_judge),
+                Err(err) => {
+                    error!("Checker Error for id: {}! :: {}", &question.id, err);
+                    return RunResult::from_error(question.id, err);
+                }
+            }
         }
+        _ => None,
     };
-    let (file_name, folder_name) = src;
-    let src_file_path = format!("tmp/{}/{}", folder_name, file_name);
-    debug!("Source file path: {}", src_file_path);
-    let file_name_no_ext = match get_file_name_without_ext(&file_name) {
-        Ok(res) => res,
+    let src = match init(&question, language.as_ref(), config).await {
+        Ok(init_response) => init_response,
         Err(err) => {
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                id: question.id,
-                error: err.to_string(),
-            });
+            error!("Something went wrong for id: {}!\n{}", &question.id, err);
+            return RunResult::from_error(question.id, err);
         }
     };
-    let mut is_compiled = false;
-    if is_compiled_language(&question.language) {
-        is_compiled = true;
+    let (sources, folder_name) = src;
+    debug!("Entry point: tmp/{}/{}", folder_name, sources.entry_point);
+    let mut compile_output = None;
+    if language.is_compiled() {
         let compilation_program = CompiledProgram {
-            src_file_path: src_file_path.clone(),
-            file_name_without_ext: format!("tmp/{}/{}", folder_name, file_name_no_ext),
-            language: question.language.clone(),
+            folder_name: folder_name.clone(),
+            sources: sources.clone(),
+            language: language.clone(),
         };
-        let compile_output = compilation_program.compile().await;
-        if let Err(err) = compile_output {
-            error!("Compilation Error for id: {}! :: {}", &question.id, err);
-            if let Err(err) = cleanup(&format!("tmp/{}", folder_name)).await {
-                return HttpResponse::InternalServerError().json(ErrorResponse {
-                    id: question.id,
-                    error: err.to_string(),
-                });
+        match compilation_program.compile(config).await {
+            Ok(stderr) => {
+                if !stderr.is_empty() {
+                    compile_output = Some(stderr);
+                }
+            }
+            Err(err) => {
+                error!("Compilation Error for id: {}! :: {}", &question.id, err);
+                if let Err(err) = cleanup(&format!("tmp/{}", folder_name)).await {
+                    return RunResult::from_error(question.id, err);
+                }
+                return RunResult::from_error(question.id, err);
             }
-            let response = ErrorResponse {
-                id: question.id,
-                error: err.to_string(),
-            };
-            return HttpResponse::BadRequest().json(response);
         }
     }
     let executed_program = Program {
-        file_name: src_file_path.clone(),
-        binary_name: if is_compiled {
-            Some(file_name_no_ext.to_string())
-        } else {
-            None
-        },
-        language: question.language.clone(),
+        sources,
+        folder_name: folder_name.clone(),
+        language: language.clone(),
         time_limit: question.timeout,
-        is_compiled,
+        memory_limit_mb: question.memory_limit_mb,
     };
-    let _run_output = executed_program.run().await;
+    let mut testcases = Vec::with_capacity(sample_testcases.len());
+    for (index, (input, expected_output)) in sample_testcases.iter().enumerate() {
+        let (run_output, interaction) = match &interactor {
+            Some(interactor) => {
+                match executed_program
+                    .interact(interactor, input, expected_output, config)
+                    .await
+                {
+                    Ok((output, judgement)) => (Ok(output), Some(judgement)),
+                    Err(err) => (Err(err), None),
+                }
+            }
+            None => (executed_program.run(input, config).await, None),
+        };
+        let testcase = match run_output {
+            Ok(output) => {
+                let judgement = if output.output_limit_exceeded {
+                    Judgement::from(Verdict::OutputLimitExceeded)
+                } else if output.resource_limit_exceeded {
+                    Judgement::from(Verdict::ResourceLimitExceeded)
+                } else if output.timed_out {
+                    Judgement::from(Verdict::TimeLimitExceeded)
+                } else if output.memory_limit_exceeded {
+                    Judgement::from(Verdict::MemoryLimitExceeded)
+                } else if let Some(interaction) = interaction {
+                    // Crashes are often the submission getting SIGPIPE once
+                    // the interactor gave up on it, which is its verdict then
+                    match interaction {
+                        Ok(judgement)
+                            if judgement.verdict != Verdict::WrongAnswer
+                                && output.exit_code != Some(0) =>
+                        {
+                            Judgement::from(Verdict::RuntimeError)
+                        }
+                        Ok(judgement) => judgement,
+                        Err(err) => judge_failure(&question.id, err),
+                    }
+                } else if output.exit_code != Some(0) {
+                    Judgement::from(Verdict::RuntimeError)
+                } else if question.stdin.is_some() {
+                    Judgement::from(Verdict::Ok)
+                } else {
+                    let actual = match &question.harness {
+                        Some(harness) => harness.result(&output.stdout),
+                        None => output.stdout.clone(),
+                    };
+                    match &special_judge {
+                        Some(special_judge) => {
+                            // Checkers read the input as the question gives it
+                            let input = &question.sample_testcases[index].0;
+                            match special_judge
+                                .judge(input, &actual, expected_output, config)
+                                .await
+                            {
+                                Ok(judgement) => judgement,
+                                Err(err) => judge_failure(&question.id, err),
+                            }
+                        }
+                        None => {
+                            let verdict = question.checker.check(&actual, expected_output);
+                            Judgement {
+                                diff: match verdict {
+                                    Verdict::WrongAnswer => diff::diff(
+                                        expected_output,
+                                        &actual,
+                                        config.diff_limit_bytes,
+                                    ),
+                                    _ => None,
+                                },
+                                ..Judgement::from(verdict)
+                            }
+                        }
+                    }
+                };
+                TestcaseResult {
+                    index,
+                    verdict: judgement.verdict,
+                    stdout: output.stdout,
+                    stderr: output.stderr,
+                    stdout_truncated: output.stdout_truncated,
+                    stderr_truncated: output.stderr_truncated,
+                    exit_code: output.exit_code,
+                    score: judgement.score,
+                    checker_message: judgement.message,
+                    diff: judgement.diff,
+                    time_ms: output.usage.wall_time_ms,
+                    cpu_time_ms: output.usage.cpu_time_ms,
+                    peak_memory_kb: output.usage.peak_memory_kb,
+                }
+            }
+            Err(err) => {
+                error!("Execution Error for id: {}! :: {}", &question.id, err);
+                TestcaseResult {
+                    index,
+                    verdict: err.verdict(),
+                    ..TestcaseResult::default()
+                }
+            }
+        };
+        testcases.push(testcase);
+    }
 
     if let Err(err) = cleanup(&format!("tmp/{}", folder_name)).await {
-        return HttpResponse::InternalServerError().json(ErrorResponse {
-            id: question.id,
-            error: err.to_string(),
-        });
+        return RunResult::from_error(question.id, err);
+    }
+    let verdict = overall_verdict(&testcases);
+    let subtasks = subtask::score(&question.subtasks, &testcases);
+    let score = if subtasks.is_empty() {
+        testcases.iter().map(|testcase| testcase.score).sum()
+    } else {
+        subtasks.iter().map(|subtask| subtask.score).sum()
+    };
+    RunResult {
+        id: question.id,
+        verdict,
+        status: verdict.code(),
+        message: verdict.message().to_string(),
+        score,
+        error: None,
+        diagnostics: compile_output
+            .as_deref()
+            .map(|stderr| diagnostics::parse(language.diagnostics_format(), stderr))
+            .unwrap_or_default(),
+        compile_output,
+        subtasks,
+        testcases,
+    }
+}
+
+// Judges a submission against the hidden testcases of a stored problem
+pub async fn submit(submission: Submission, config: &Config) -> SubmitResult {
+    let problem = match Problem::load(&submission.problem, config).await {
+        Ok(problem) => problem,
+        Err(err) => {
+            error!("Something went wrong for id: {}!\n{}", &submission.id, err);
+            return RunResult::from_error(submission.id, err).into();
+        }
+    };
+    execute(problem.question(submission), config).await.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::reason;
+
+    #[test]
+    fn relative_path_normalises() {
+        assert_eq!(relative_path("main.py").unwrap(), "main.py");
+        assert_eq!(relative_path("./src/./main.rs").unwrap(), "src/main.rs");
+        assert_eq!(relative_path("src//lib/").unwrap(), "src/lib");
+    }
+
+    fn files(paths: &[&str]) -> Files {
+        paths
+            .iter()
+            .map(|path| (path.to_string(), Vec::new()))
+            .collect()
+    }
+
+    #[test]
+    fn paths_can_share_folders() {
+        assert!(check_paths(&files(&["main.py", "lib/a.py", "lib/b.py", "lib2"])).is_ok());
+    }
+
+    #[test]
+    fn paths_are_submitted_once() {
+        assert_eq!(
+            reason(check_paths(&files(&["a.py", "main.py", "a.py"]))),
+            "`a.py` is submitted more than once"
+        );
+    }
+
+    #[test]
+    fn paths_are_not_inside_files() {
+        assert_eq!(
+            reason(check_paths(&files(&["a/b/c.py", "a"]))),
+            "`a/b/c.py` can't be in `a`, which is a file"
+        );
+        assert_eq!(
+            reason(check_paths(&files(&["a/b", "a/b/c.py"]))),
+            "`a/b/c.py` can't be in `a/b`, which is a file"
+        );
+    }
+
+    #[test]
+    fn memory_limit_is_within_the_server_maximum() {
+        let config = Config {
+            max_memory_limit_mb: 512,
+            ..testing::config()
+        };
+        assert_eq!(memory_limit_bytes(256, &config).unwrap(), 256 << 20);
+        assert_eq!(memory_limit_bytes(512, &config).unwrap(), 512 << 20);
+        for mb in [0, 513, u64::MAX] {
+            assert_eq!(
+                reason(memory_limit_bytes(mb, &config)),
+                format!(
+                    "`memory_limit_mb` has to be between 1 and 512 MB, got {}",
+                    mb
+                )
+            );
+        }
+        let config = Config {
+            max_memory_limit_mb: u64::MAX,
+            ..config
+        };
+        assert!(memory_limit_bytes(u64::MAX, &config).is_err());
+    }
+
+    #[test]
+    fn relative_path_stays_in_the_submission() {
+        for path in ["", ".", "./", "../main.py", "src/../../x", "/etc/passwd"] {
+            assert_eq!(
+                reason(relative_path(path)),
+                format!("`{}` is not a valid file path", path)
+            );
+        }
     }
-    HttpResponse::Ok().json(String::from("Compilation successful"))
 }
@@ -1,7 +1,9 @@
 mod routes;
-use actix_web::{middleware::Logger, App, HttpServer};
+use actix_web::{middleware::Logger, web, App, HttpServer};
 use env_logger::Env;
+use log::{error, warn};
 use routes::config;
+use rustycoding::config::Config;
 
 // use rustycoding::execute;
 
@@ -12,9 +14,31 @@ async fn main() -> std::io::Result<()> {
         .parse()
         .unwrap();
     env_logger::init_from_env(Env::default().default_filter_or("info"));
-    HttpServer::new(|| {
+    let mut settings = match Config::from_env() {
+        Ok(settings) => settings,
+        Err(err) => {
+            error!("{}", err);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                err.to_string(),
+            ));
+        }
+    };
+    settings.detect_languages().await;
+    let settings = web::Data::new(settings);
+    if !settings.process_limit_enforced() {
+        warn!(
+            "Nothing limits how many processes submissions start, a fork bomb can take the \
+             server down. Set CGROUP_ROOT, or run as an unprivileged user with SANDBOX set to \
+             namespaces, nsjail or isolate."
+        );
+    }
+    HttpServer::new(move || {
         let logger = Logger::new("\"%r\" %s (%b bytes) %Dms");
-        App::new().wrap(logger).configure(config)
+        App::new()
+            .wrap(logger)
+            .app_data(settings.clone())
+            .configure(config)
     })
     .bind(("0.0.0.0", port))?
     .run()
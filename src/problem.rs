@@ -0,0 +1,85 @@
+use serde::Deserialize;
+use std::io::ErrorKind;
+
+use crate::{
+    checker::{Checker, CheckerProgram},
+    config::Config,
+    harness::Harness,
+    subtask::Subtask,
+    types::{CodingError, Question, Submission},
+};
+
+// Problems stored on the server for `/submit`, whose testcases are never
+// sent to or shown to whoever submits. Each one is a JSON file in
+// `PROBLEMS_DIR` named after the problem, holding what a question would
+// besides the submission itself.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Problem {
+    #[serde(default)]
+    pub harness: Option<Harness>,
+    #[serde(default)]
+    pub checker: Checker,
+    #[serde(default)]
+    pub interactor: Option<CheckerProgram>,
+    pub timeout: i32,
+    #[serde(default)]
+    pub memory_limit_mb: Option<u64>,
+    pub testcases: Vec<(String, String)>,
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+}
+
+fn unknown(problem: &str) -> CodingError {
+    CodingError::UnknownProblem {
+        problem: problem.to_string(),
+    }
+}
+
+impl Problem {
+    pub async fn load(name: &str, config: &Config) -> Result<Problem, CodingError> {
+        // Names can't reach outside the problems folder
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        let dir = match &config.problems_dir {
+            Some(dir) if valid => dir,
+            _ => return Err(unknown(name)),
+        };
+        let path = dir.join(format!("{}.json", name));
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Err(unknown(name)),
+            Err(err) => {
+                return Err(CodingError::InvalidConfig {
+                    reason: format!("Unable to read {} :: {}", path.display(), err),
+                })
+            }
+        };
+        serde_json::from_slice(&bytes).map_err(|err| CodingError::InvalidConfig {
+            reason: format!("Invalid problem {} :: {}", path.display(), err),
+        })
+    }
+
+    pub fn question(self, submission: Submission) -> Question {
+        Question {
+            id: submission.id,
+            language: submission.language,
+            language_version: submission.language_version,
+            source_code: submission.source_code,
+            files: submission.files,
+            entry_point: submission.entry_point,
+            archive: submission.archive,
+            harness: self.harness,
+            checker: self.checker,
+            interactor: self.interactor,
+            timeout: self.timeout,
+            memory_limit_mb: self.memory_limit_mb,
+            sample_testcases: self.testcases,
+            stdin: None,
+            subtasks: self.subtasks,
+        }
+    }
+}
@@ -1,12 +1,26 @@
-use actix_web::{get, post, web, HttpResponse, Responder};
+use actix_multipart::{Field, Multipart};
+use actix_web::{get, guard::GuardContext, post, web, HttpResponse, Responder};
+use futures_util::TryStreamExt;
 use log::info;
-use rustycoding::{execute, types::Question};
-use serde::Deserialize;
+use rustycoding::{
+    config::Config,
+    execute, submit,
+    types::{Question, Submission, Verdict},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+// The manifest is a question without its files, it stays small
+const MANIFEST_SIZE_LIMIT: usize = 1024 * 1024;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(index);
     cfg.service(check);
+    // Checked first, uploads would otherwise be turned away for not being JSON
+    cfg.service(run_archive);
     cfg.service(run);
+    cfg.service(submit_archive);
+    cfg.service(submit_question);
+    cfg.service(languages);
 }
 
 #[get("/")]
@@ -28,8 +42,127 @@ async fn check(info: web::Query<CheckQuery>) -> impl Responder {
     }
 }
 
+#[get("/languages")]
+async fn languages(config: web::Data<Config>) -> impl Responder {
+    HttpResponse::Ok().json(config.languages.list())
+}
+
+fn respond(verdict: Verdict, result: impl Serialize) -> HttpResponse {
+    match verdict {
+        Verdict::InternalServerError => HttpResponse::InternalServerError().json(result),
+        Verdict::InvalidFile | Verdict::FileNotFound => HttpResponse::BadRequest().json(result),
+        _ => HttpResponse::Ok().json(result),
+    }
+}
+
+// Runs the testcases sent along with the code, showing everything about
+// each run
 #[post("/run")]
-async fn run(body: web::Json<Question>) -> impl Responder {
-    let question = body.into_inner();
-    execute(question).await
+async fn run(body: web::Json<Question>, config: web::Data<Config>) -> impl Responder {
+    let result = execute(body.into_inner(), &config).await;
+    respond(result.verdict, result)
+}
+
+// Judges the code against a stored problem, with its testcases kept hidden
+#[post("/submit")]
+async fn submit_question(body: web::Json<Submission>, config: web::Data<Config>) -> impl Responder {
+    let result = submit(body.into_inner(), &config).await;
+    respond(result.verdict, result)
+}
+
+fn is_multipart(ctx: &GuardContext) -> bool {
+    ctx.head()
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"))
+}
+
+// Reads a whole form field, giving up once it goes over the limit
+async fn read_field(field: &mut Field, limit: usize) -> Result<Option<Vec<u8>>, HttpResponse> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field
+        .try_next()
+        .await
+        .map_err(|err| HttpResponse::BadRequest().body(err.to_string()))?
+    {
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+// Projects too big to inline in `source_code` are uploaded as a zip or
+// tar.gz `archive`, along with a `manifest` holding the rest of the request
+async fn read_upload<T: DeserializeOwned>(
+    mut payload: Multipart,
+    config: &Config,
+) -> Result<(T, Vec<u8>), HttpResponse> {
+    let mut manifest = None;
+    let mut archive = None;
+    while let Some(mut field) = payload
+        .try_next()
+        .await
+        .map_err(|err| HttpResponse::BadRequest().body(err.to_string()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let limit = match name.as_str() {
+            "manifest" => MANIFEST_SIZE_LIMIT,
+            "archive" => config.archive_size_limit_bytes as usize,
+            _ => {
+                return Err(HttpResponse::BadRequest().body(format!(
+                    "Unexpected field `{}`, expected `manifest` and `archive`",
+                    name
+                )))
+            }
+        };
+        let bytes = match read_field(&mut field, limit).await? {
+            Some(bytes) => bytes,
+            None => {
+                return Err(HttpResponse::PayloadTooLarge()
+                    .body(format!("`{}` is larger than {} bytes", name, limit)))
+            }
+        };
+        if name == "manifest" {
+            manifest = Some(bytes);
+        } else {
+            archive = Some(bytes);
+        }
+    }
+    let manifest = match manifest {
+        Some(manifest) => serde_json::from_slice(&manifest).map_err(|err| {
+            HttpResponse::BadRequest().body(format!("Invalid manifest :: {}", err))
+        })?,
+        None => return Err(HttpResponse::BadRequest().body("The `manifest` field is missing")),
+    };
+    match archive {
+        Some(archive) => Ok((manifest, archive)),
+        None => Err(HttpResponse::BadRequest().body("The `archive` field is missing")),
+    }
+}
+
+#[post("/run", guard = "is_multipart")]
+async fn run_archive(payload: Multipart, config: web::Data<Config>) -> impl Responder {
+    match read_upload::<Question>(payload, &config).await {
+        Ok((mut question, archive)) => {
+            question.archive = Some(archive);
+            let result = execute(question, &config).await;
+            respond(result.verdict, result)
+        }
+        Err(response) => response,
+    }
+}
+
+#[post("/submit", guard = "is_multipart")]
+async fn submit_archive(payload: Multipart, config: web::Data<Config>) -> impl Responder {
+    match read_upload::<Submission>(payload, &config).await {
+        Ok((mut submission, archive)) => {
+            submission.archive = Some(archive);
+            let result = submit(submission, &config).await;
+            respond(result.verdict, result)
+        }
+        Err(response) => response,
+    }
 }
@@ -0,0 +1,676 @@
+use log::{debug, error};
+use std::{
+    io::{PipeReader, PipeWriter},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::process::CommandExt,
+    },
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::{ChildStderr, ChildStdin, ChildStdout},
+    sync::Notify,
+};
+
+use crate::{
+    backend, cgroup,
+    cgroup::Cgroup,
+    config::{Config, SandboxKind},
+    sandbox::Sandbox,
+    types::{CodingError, Limits},
+};
+
+// Single entry point for running a submission, whatever its language.
+// Compiled binaries and interpreters alike go through `run` so that the
+// time limit and the captured output behave the same for every language.
+
+#[derive(Default)]
+pub struct ProcessOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub memory_limit_exceeded: bool,
+    // Hit the process, open file or file size limit
+    pub resource_limit_exceeded: bool,
+    pub output_limit_exceeded: bool,
+    pub usage: ResourceUsage,
+}
+
+#[derive(Default)]
+pub struct ResourceUsage {
+    pub wall_time_ms: u64,
+    pub cpu_time_ms: u64,
+    pub peak_memory_kb: u64,
+}
+
+fn timeval_to_ms(tv: libc::timeval) -> u64 {
+    tv.tv_sec as u64 * 1000 + tv.tv_usec as u64 / 1000
+}
+
+// Messages printed by common runtimes when an allocation fails. Without a
+// cgroup to tell us about OOM kills, they are the only hint that a crash
+// was caused by the memory limit.
+const OUT_OF_MEMORY_PATTERNS: [&str; 8] = [
+    "MemoryError",
+    "std::bad_alloc",
+    "memory allocation of",
+    "OutOfMemoryError",
+    "out of memory",
+    "Cannot allocate memory",
+    "failed to allocate memory",
+    "insufficient memory",
+];
+
+// Only looked for on stdout, where the JVM writes it, as programs print
+// all sorts of things there
+const JVM_OUT_OF_MEMORY: &str =
+    "There is insufficient memory for the Java Runtime Environment to continue.";
+
+// Same for the process, open file and file size limits, each only looked
+// for when that limit applies. Programs that don't ignore SIGXFSZ are killed
+// by it instead, which `run` checks for directly.
+const PROCESS_LIMIT_PATTERNS: [&str; 3] = [
+    "Resource temporarily unavailable",
+    "unable to create native thread",
+    "fork: retry",
+];
+const OPEN_FILES_LIMIT_PATTERNS: [&str; 1] = ["Too many open files"];
+const FILE_SIZE_LIMIT_PATTERNS: [&str; 1] = ["File too large"];
+// How long the output can take to reach EOF once the program was reaped
+const ESCAPE_GRACE: Duration = Duration::from_millis(500);
+
+pub fn set_rlimit(resource: libc::__rlimit_resource_t, limit: u64) -> std::io::Result<()> {
+    let rlim = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        rlim_max: limit as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &rlim) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// One end of the pipes between a submission and the interactor of an
+// interactive problem, see `connect`
+pub struct Connection {
+    stdin: PipeReader,
+    stdout: PipeWriter,
+}
+
+// Pipes connecting two programs, each one reading on its stdin what the
+// other writes to its stdout
+pub fn connect() -> Result<(Connection, Connection), CodingError> {
+    let pipes = std::io::pipe().and_then(|first| Ok((first, std::io::pipe()?)));
+    match pipes {
+        Ok(((first_reader, first_writer), (second_reader, second_writer))) => Ok((
+            Connection {
+                stdin: first_reader,
+                stdout: second_writer,
+            },
+            Connection {
+                stdin: second_reader,
+                stdout: first_writer,
+            },
+        )),
+        Err(err) => {
+            error!("Unable to create pipes :: {}", err);
+            Err(CodingError::ProcessError)
+        }
+    }
+}
+
+// What the program's stdin and stdout are hooked up to
+enum Io<'a> {
+    Input(&'a str),
+    Connected(Connection),
+}
+
+// Spawns `command` in `workdir` as the leader of a new process group with
+// piped stdio, feeds it `input` and waits for it within `limits`. With any
+// sandbox enabled, `workdir` is what the program sees as /box.
+//
+// Memory is limited through a cgroup when one is available. Otherwise we fall
+// back to RLIMIT_DATA, which unlike RLIMIT_AS doesn't count the large
+// PROT_NONE reservations made by the JVM, Go and V8.
+//
+// The process count is limited by the cgroup as well, or by RLIMIT_NPROC
+// inside a sandbox. Once the submission exits, its process group is killed,
+// and with a cgroup or a PID namespace whatever else is left of it too.
+// Otherwise processes that left the group are only found while they hold
+// the output pipes: those are killed and the run is a resource limit
+// violation, the others are out of reach and keep running.
+pub async fn run(
+    command: Command,
+    workdir: &Path,
+    input: &str,
+    limits: &Limits,
+    config: &Config,
+) -> Result<ProcessOutput, CodingError> {
+    spawn_and_wait(command, workdir, Io::Input(input), limits, config).await
+}
+
+// Same as `run`, with stdin and stdout going to the other end of the
+// connection rather than to us. Only stderr is captured.
+pub async fn run_connected(
+    command: Command,
+    workdir: &Path,
+    connection: Connection,
+    limits: &Limits,
+    config: &Config,
+) -> Result<ProcessOutput, CodingError> {
+    spawn_and_wait(command, workdir, Io::Connected(connection), limits, config).await
+}
+
+async fn spawn_and_wait(
+    mut command: Command,
+    workdir: &Path,
+    io: Io<'_>,
+    limits: &Limits,
+    config: &Config,
+) -> Result<ProcessOutput, CodingError> {
+    let mut cleanup = None;
+    let sandbox = match config.sandbox {
+        SandboxKind::None => {
+            command.current_dir(workdir);
+            None
+        }
+        SandboxKind::Namespaces => {
+            match Sandbox::new(workdir, &config.sandbox_readonly_paths, limits.processes) {
+                Ok(sandbox) => Some(Arc::new(sandbox)),
+                Err(err) => {
+                    error!("Unable to prepare the sandbox :: {}", err);
+                    return Err(CodingError::ProcessError);
+                }
+            }
+        }
+        SandboxKind::Bubblewrap | SandboxKind::Nsjail | SandboxKind::Isolate => {
+            let backend = match backend::from_config(config) {
+                Some(backend) => backend,
+                None => return Err(CodingError::ProcessError),
+            };
+            // isolate sets its box up with a blocking command
+            let (workdir, wrap_limits) = (workdir.to_path_buf(), limits.clone());
+            let wrapped =
+                tokio::task::spawn_blocking(move || backend.wrap(&command, &workdir, &wrap_limits))
+                    .await
+                    .unwrap_or_else(|err| Err(std::io::Error::other(err)));
+            match wrapped {
+                Ok(wrapped) => {
+                    command = wrapped.command;
+                    cleanup = wrapped.cleanup;
+                }
+                Err(err) => {
+                    error!("Unable to prepare the sandbox :: {}", err);
+                    return Err(CodingError::ProcessError);
+                }
+            }
+            None
+        }
+    };
+    let cgroup = match &config.cgroup_root {
+        Some(root) => Cgroup::create(root, limits),
+        None => None,
+    };
+    let procs_path = cgroup.as_ref().and_then(|cgroup| cgroup.procs_path());
+    let data_limit = match cgroup {
+        Some(_) => None,
+        None => limits.memory_bytes,
+    };
+    let open_files = limits.open_files;
+    let file_size = limits.file_size_bytes;
+    let child_sandbox = sandbox.clone();
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(procs_path) = &procs_path {
+                cgroup::join(procs_path)?;
+            }
+            if let Some(limit) = data_limit {
+                set_rlimit(libc::RLIMIT_DATA, limit)?;
+            }
+            if let Some(limit) = open_files {
+                set_rlimit(libc::RLIMIT_NOFILE, limit)?;
+            }
+            if let Some(limit) = file_size {
+                set_rlimit(libc::RLIMIT_FSIZE, limit)?;
+            }
+            // Has to come last, it also installs the seccomp filter
+            if let Some(sandbox) = &child_sandbox {
+                sandbox.enter()?;
+            }
+            Ok(())
+        });
+    }
+    let input = match io {
+        Io::Input(input) => {
+            command.stdin(Stdio::piped()).stdout(Stdio::piped());
+            input
+        }
+        Io::Connected(connection) => {
+            command.stdin(connection.stdin).stdout(connection.stdout);
+            ""
+        }
+    };
+    let child = command.stderr(Stdio::piped()).process_group(0).spawn();
+    // Also closes our copies of the connection, so each side sees EOF once
+    // the other one exits
+    drop(command);
+    let output = match child {
+        Ok(child) => wait_with_timeout(child, input, limits, cgroup.as_ref()).await,
+        Err(err) => {
+            error!("Unable to spawn child process :: {}", err);
+            Err(CodingError::ProcessError)
+        }
+    };
+    if let Some(sandbox) = sandbox {
+        sandbox.remove();
+    }
+    if let Some(cleanup) = cleanup {
+        let _ = tokio::task::spawn_blocking(cleanup).await;
+    }
+    let oom_killed = cgroup.as_ref().is_some_and(|cgroup| cgroup.oom_killed());
+    let pids_limit_reached = cgroup
+        .as_ref()
+        .is_some_and(|cgroup| cgroup.pids_limit_reached());
+    // ru_maxrss only covers the largest single process, the cgroup knows
+    // about the whole tree
+    let cgroup_peak = cgroup.as_ref().and_then(|cgroup| cgroup.memory_peak());
+    let has_cgroup = cgroup.is_some();
+    if let Some(cgroup) = cgroup {
+        cgroup.remove().await;
+    }
+    let mut output = output?;
+    if let Some(peak) = cgroup_peak {
+        output.usage.peak_memory_kb = peak / 1024;
+    }
+    output.memory_limit_exceeded = limits.memory_bytes.is_some()
+        && output.exit_code != Some(0)
+        && (oom_killed
+            || OUT_OF_MEMORY_PATTERNS
+                .iter()
+                .any(|pattern| output.stderr.contains(pattern))
+            // The JVM reports failed native allocations on stdout
+            || output.stdout.contains(JVM_OUT_OF_MEMORY));
+    // A cgroup tells exactly when the process limit was hit, without one
+    // it only counts when RLIMIT_NPROC applies
+    let process_rlimit =
+        !has_cgroup && limits.processes.is_some() && config.process_limit_enforced();
+    let reported = |patterns: &[&str]| {
+        output.exit_code != Some(0)
+            && patterns
+                .iter()
+                .any(|pattern| output.stderr.contains(pattern))
+    };
+    let limit_reported = (process_rlimit && reported(&PROCESS_LIMIT_PATTERNS))
+        || (limits.open_files.is_some() && reported(&OPEN_FILES_LIMIT_PATTERNS))
+        || (limits.file_size_bytes.is_some() && reported(&FILE_SIZE_LIMIT_PATTERNS));
+    output.resource_limit_exceeded =
+        output.resource_limit_exceeded || pids_limit_reached || limit_reported;
+    Ok(output)
+}
+
+async fn write_input(stdin: Option<ChildStdin>, input: String) -> Result<(), CodingError> {
+    // Dropping stdin closes the pipe so the program sees EOF
+    if let Some(mut stdin) = stdin {
+        if let Err(err) = stdin.write_all(input.as_bytes()).await {
+            // The program may exit without reading its input
+            if err.kind() != std::io::ErrorKind::BrokenPipe {
+                error!("Unable to write to stdin of child process :: {}", err);
+                return Err(CodingError::ProcessError);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Output of a single stream, cut off at the output limit
+struct Captured {
+    text: String,
+    truncated: bool,
+}
+
+// Reads the pipe until EOF or until it has produced more than `limit` bytes,
+// in which case what was read so far is kept and `limit_hit` is notified so
+// the process gets killed.
+async fn read_pipe<R: AsyncRead + Unpin>(
+    pipe: Option<R>,
+    limit: Option<u64>,
+    limit_hit: Arc<Notify>,
+) -> Result<Captured, CodingError> {
+    let mut buf = Vec::new();
+    let mut truncated = false;
+    if let Some(mut pipe) = pipe {
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        let mut chunk = [0u8; 8192];
+        loop {
+            let read = match pipe.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(_) => return Err(CodingError::InvalidStringFromConsole),
+            };
+            if buf.len() + read > limit {
+                buf.extend_from_slice(&chunk[..limit - buf.len()]);
+                truncated = true;
+                limit_hit.notify_one();
+                break;
+            }
+            buf.extend_from_slice(&chunk[..read]);
+        }
+    }
+    let text = match String::from_utf8(buf) {
+        Ok(text) => text,
+        // The cut may have landed in the middle of a character
+        Err(err) if truncated && err.utf8_error().error_len().is_none() => {
+            let valid = err.utf8_error().valid_up_to();
+            String::from_utf8_lossy(&err.as_bytes()[..valid]).into_owned()
+        }
+        Err(_) => return Err(CodingError::InvalidStringFromConsole),
+    };
+    Ok(Captured { text, truncated })
+}
+
+// Blocks until the process has exited without reaping it, so its pid
+// (and with it the process group id) can't be reused while we still
+// have to signal the group.
+fn wait_for_exit(pid: libc::pid_t) -> std::io::Result<()> {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let res = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if res == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+fn kill_process_group(pgid: libc::pid_t) {
+    // ESRCH only means every process in the group is already gone
+    unsafe {
+        libc::killpg(pgid, libc::SIGKILL);
+    }
+}
+
+fn pipe_inode(fd: RawFd) -> Option<u64> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.st_ino)
+}
+
+// Kills every process, besides us, with the writing end of one of the
+// pipes open. Whatever our own forks inherit between fork and exec is the
+// reading end, so other submissions being spawned are left alone.
+fn kill_pipe_writers(pipes: &[u64]) {
+    let links: Vec<String> = pipes
+        .iter()
+        .map(|inode| format!("pipe:[{}]", inode))
+        .collect();
+    let ours = std::process::id();
+    let processes = match std::fs::read_dir("/proc") {
+        Ok(processes) => processes,
+        Err(err) => {
+            error!("Unable to list processes :: {}", err);
+            return;
+        }
+    };
+    for process in processes.flatten() {
+        let pid = match process.file_name().to_str().map(str::parse::<u32>) {
+            Some(Ok(pid)) if pid != ours => pid,
+            _ => continue,
+        };
+        let fds = match std::fs::read_dir(process.path().join("fd")) {
+            Ok(fds) => fds,
+            // Gone already
+            Err(_) => continue,
+        };
+        let writes = fds.flatten().any(|fd| {
+            let holds = std::fs::read_link(fd.path())
+                .is_ok_and(|target| links.iter().any(|link| target.as_os_str() == link.as_str()));
+            let info = process.path().join("fdinfo").join(fd.file_name());
+            holds && std::fs::read_to_string(info).is_ok_and(|info| writable(&info))
+        });
+        if writes {
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+// Whether /proc/<pid>/fdinfo/<fd> describes a descriptor open for writing
+fn writable(fdinfo: &str) -> bool {
+    fdinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("flags:"))
+        .and_then(|flags| u32::from_str_radix(flags.trim(), 8).ok())
+        .is_some_and(|flags| flags as libc::c_int & libc::O_ACCMODE != libc::O_RDONLY)
+}
+
+// Reaps the process and returns its wait status along with the resources
+// it and its reaped descendants used.
+fn reap(pid: libc::pid_t) -> std::io::Result<(libc::c_int, libc::rusage)> {
+    loop {
+        let mut status: libc::c_int = 0;
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        let res = unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) };
+        if res == pid {
+            return Ok((status, rusage));
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+// Feeds `input` to the child and collects its output, killing the child's
+// whole process group once the time limit expires or either stream goes
+// past the output limit. The child has to be spawned as the leader of its
+// own process group.
+//
+// Once it is reaped, its output has to be drained within `ESCAPE_GRACE`.
+// Past that, something that left the group still holds the pipes: whatever
+// holds them is killed and the run counts as a resource limit violation.
+//
+// The child is a std one and is reaped with wait4 rather than through
+// tokio, which would not give us its resource usage.
+async fn wait_with_timeout(
+    mut child: Child,
+    input: &str,
+    limits: &Limits,
+    cgroup: Option<&Cgroup>,
+) -> Result<ProcessOutput, CodingError> {
+    let start = Instant::now();
+    let pid = child.id() as libc::pid_t;
+    let output_pipes: Vec<u64> = [
+        child.stdout.as_ref().map(AsRawFd::as_raw_fd),
+        child.stderr.as_ref().map(AsRawFd::as_raw_fd),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(pipe_inode)
+    .collect();
+    let stdin = child
+        .stdin
+        .take()
+        .and_then(|pipe| ChildStdin::from_std(pipe).ok());
+    let stdout = child
+        .stdout
+        .take()
+        .and_then(|pipe| ChildStdout::from_std(pipe).ok());
+    let stderr = child
+        .stderr
+        .take()
+        .and_then(|pipe| ChildStderr::from_std(pipe).ok());
+    let mut writer = tokio::spawn(write_input(stdin, input.to_string()));
+    let limit_hit = Arc::new(Notify::new());
+    let mut stdout_reader = tokio::spawn(read_pipe(stdout, limits.output_bytes, limit_hit.clone()));
+    let mut stderr_reader = tokio::spawn(read_pipe(stderr, limits.output_bytes, limit_hit.clone()));
+
+    let exited = tokio::task::spawn_blocking(move || wait_for_exit(pid));
+    let waited = async {
+        tokio::select! {
+            _ = exited => {}
+            _ = limit_hit.notified() => {}
+        }
+    };
+    let timed_out = tokio::time::timeout(limits.time, waited).await.is_err();
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+    // Leftover background processes would otherwise keep the pipes open.
+    // Those that left the group are dealt with below, whether the program
+    // exited or timed out.
+    kill_process_group(pid);
+    if let Some(cgroup) = cgroup {
+        cgroup.kill();
+    }
+    let (status, rusage) = match tokio::task::spawn_blocking(move || reap(pid)).await {
+        Ok(Ok(reaped)) => reaped,
+        _ => return Err(CodingError::ProcessError),
+    };
+    let usage = ResourceUsage {
+        wall_time_ms,
+        cpu_time_ms: timeval_to_ms(rusage.ru_utime) + timeval_to_ms(rusage.ru_stime),
+        // Linux reports ru_maxrss in kilobytes
+        peak_memory_kb: rusage.ru_maxrss as u64,
+    };
+    // Processes that left the group, without a cgroup or a PID namespace to
+    // catch them, can hold the pipes open for as long as they like
+    let drained = tokio::time::timeout(ESCAPE_GRACE, async {
+        tokio::join!(&mut writer, &mut stdout_reader, &mut stderr_reader)
+    })
+    .await;
+    let escaped = drained.is_err();
+    if escaped {
+        debug!("Processes of {} outlived it, killing them", pid);
+        let _ = tokio::task::spawn_blocking(move || kill_pipe_writers(&output_pipes)).await;
+        writer.abort();
+        stdout_reader.abort();
+        stderr_reader.abort();
+    }
+    if timed_out || escaped {
+        return Ok(ProcessOutput {
+            timed_out,
+            resource_limit_exceeded: escaped,
+            usage,
+            ..ProcessOutput::default()
+        });
+    }
+
+    let (writer, stdout, stderr) = match drained {
+        Ok((Ok(writer), Ok(stdout), Ok(stderr))) => (writer, stdout, stderr),
+        _ => return Err(CodingError::ProcessError),
+    };
+    writer?;
+    let (stdout, stderr) = (stdout?, stderr?);
+    debug!("{}", stderr.text);
+    Ok(ProcessOutput {
+        stdout: stdout.text,
+        stderr: stderr.text,
+        stdout_truncated: stdout.truncated,
+        stderr_truncated: stderr.truncated,
+        output_limit_exceeded: stdout.truncated || stderr.truncated,
+        exit_code: if libc::WIFEXITED(status) {
+            Some(libc::WEXITSTATUS(status))
+        } else {
+            None
+        },
+        timed_out,
+        memory_limit_exceeded: false,
+        resource_limit_exceeded: libc::WIFSIGNALED(status)
+            && libc::WTERMSIG(status) == libc::SIGXFSZ,
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    // Whether the process is gone, or only waiting to be reaped by whoever
+    // inherited it, within a moment for the signal to land
+    fn dead(pid: &str) -> bool {
+        let stat = format!("/proc/{}/stat", pid.trim());
+        (0..20).any(|_| {
+            let dead = std::fs::read_to_string(&stat).map_or(true, |stat| {
+                stat.rsplit(") ")
+                    .next()
+                    .is_some_and(|state| state.starts_with('Z'))
+            });
+            if !dead {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            dead
+        })
+    }
+
+    async fn run_escaping(script: &str, time: Duration) -> (ProcessOutput, Duration, bool) {
+        let config = testing::config();
+        let workdir = testing::workdir();
+        // Leaves the process group, holding stdout, and says who it is
+        let script = format!(
+            "setsid sh -c 'echo $$ > escaped; exec sleep 30' & {}",
+            script
+        );
+        let mut command = Command::new("sh");
+        command.args(["-c", &script]);
+        let limits = config.limits(time, None);
+        let start = Instant::now();
+        let output = run(command, &workdir, "", &limits, &config).await.unwrap();
+        let elapsed = start.elapsed();
+        let escaped = std::fs::read_to_string(workdir.join("escaped")).unwrap();
+        let _ = std::fs::remove_dir_all(&workdir);
+        (output, elapsed, dead(&escaped))
+    }
+
+    #[actix_web::test]
+    async fn kills_processes_left_holding_the_output() {
+        let (output, elapsed, killed) =
+            run_escaping("sleep 0.2; echo done", Duration::from_secs(5)).await;
+        assert!(elapsed < Duration::from_secs(2));
+        assert!(killed);
+        assert!(output.resource_limit_exceeded);
+        assert!(!output.timed_out);
+    }
+
+    #[actix_web::test]
+    async fn kills_processes_left_holding_the_output_on_timeout() {
+        let (output, elapsed, killed) = run_escaping("sleep 30", Duration::from_secs(1)).await;
+        assert!(elapsed < Duration::from_secs(3));
+        assert!(killed);
+        assert!(output.timed_out);
+    }
+
+    #[actix_web::test]
+    async fn drains_the_output_of_well_behaved_programs() {
+        let config = testing::config();
+        let workdir = testing::workdir();
+        let mut command = Command::new("sh");
+        command.args(["-c", "sleep 0.1 & cat; wait"]);
+        let limits = config.limits(Duration::from_secs(5), None);
+        let output = run(command, &workdir, "input", &limits, &config)
+            .await
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&workdir);
+        assert_eq!(output.stdout, "input");
+        assert_eq!(output.exit_code, Some(0));
+        assert!(!output.resource_limit_exceeded);
+    }
+}
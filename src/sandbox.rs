@@ -0,0 +1,480 @@
+use log::error;
+use std::{
+    collections::BTreeSet,
+    ffi::{CStr, CString},
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    ptr,
+};
+use uuid::Uuid;
+
+use crate::{runner::set_rlimit, seccomp};
+
+// Built-in sandbox for untrusted code. The submission runs in fresh user,
+// PID, mount, network, IPC and UTS namespaces, inside a new root made of
+// read-only binds of a few host directories, a writable bind of its own
+// folder at /box, a private /tmp and a minimal /dev. It has no capabilities
+// left and is restricted to the syscalls allowed in `seccomp`.
+//
+// Everything that allocates is prepared by `Sandbox::new` in the server,
+// `enter` then only makes async-signal-safe calls between fork and exec.
+
+const DEVICES: [&str; 5] = [
+    "/dev/null",
+    "/dev/zero",
+    "/dev/full",
+    "/dev/random",
+    "/dev/urandom",
+];
+
+enum Step {
+    Mkdir(CString),
+    Touch(CString),
+    Symlink {
+        target: CString,
+        path: CString,
+    },
+    Mount {
+        source: Option<CString>,
+        target: CString,
+        fstype: Option<CString>,
+        flags: libc::c_ulong,
+        data: Option<CString>,
+    },
+}
+
+pub struct Sandbox {
+    root: PathBuf,
+    root_path: CString,
+    uid_map: CString,
+    gid_map: CString,
+    process_limit: Option<u64>,
+    steps: Vec<Step>,
+    filter: Vec<libc::sock_filter>,
+}
+
+fn cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+// `root` joined with the absolute `path`
+fn under(root: &Path, path: &Path) -> PathBuf {
+    root.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+fn check(res: libc::c_int) -> io::Result<()> {
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Flags of the mount backing `path` that a user namespace isn't allowed to
+// clear, and therefore has to repeat when remounting a bind of it.
+fn locked_flags(path: &Path) -> io::Result<libc::c_ulong> {
+    let path = cstring(path)?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    check(unsafe { libc::statvfs(path.as_ptr(), &mut stat) })?;
+    let mut flags = 0;
+    for (st, ms) in [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ] {
+        if stat.f_flag & st != 0 {
+            flags |= ms;
+        }
+    }
+    Ok(flags)
+}
+
+impl Sandbox {
+    pub fn new(
+        workdir: &Path,
+        readonly_paths: &[PathBuf],
+        process_limit: Option<u64>,
+    ) -> io::Result<Sandbox> {
+        let workdir = fs::canonicalize(workdir)?;
+        // Cached checkers run in the same folder for every request at once
+        let root = PathBuf::from(format!("{}.{}.root", workdir.display(), Uuid::new_v4()));
+        fs::create_dir(&root)?;
+
+        let mut sandbox = Sandbox {
+            root_path: cstring(&root)?,
+            root,
+            uid_map: CString::new(format!("0 {} 1", unsafe { libc::getuid() }))?,
+            gid_map: CString::new(format!("0 {} 1", unsafe { libc::getgid() }))?,
+            process_limit,
+            steps: Vec::new(),
+            filter: seccomp::allowlist_filter(),
+        };
+        let mut dirs = BTreeSet::new();
+
+        // Keep our mounts from propagating back to the host
+        sandbox.mount(
+            None,
+            Path::new("/"),
+            None,
+            libc::MS_REC | libc::MS_PRIVATE,
+            None,
+        )?;
+        let root = sandbox.root.clone();
+        sandbox.mount(
+            Some("tmpfs"),
+            &root,
+            Some("tmpfs"),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            Some("mode=0755"),
+        )?;
+
+        for path in readonly_paths {
+            if !path.is_absolute() {
+                error!("Ignoring relative sandbox path {}", path.display());
+                continue;
+            }
+            let metadata = match fs::symlink_metadata(path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let target = under(&root, path);
+            if let Some(parent) = target.parent() {
+                sandbox.mkdir_all(&mut dirs, parent)?;
+            }
+            if metadata.file_type().is_symlink() {
+                // Merged /usr systems link /bin, /lib, ... into /usr
+                sandbox.steps.push(Step::Symlink {
+                    target: cstring(&fs::read_link(path)?)?,
+                    path: cstring(&target)?,
+                });
+                continue;
+            }
+            if metadata.is_dir() {
+                sandbox.mkdir_all(&mut dirs, &target)?;
+            } else {
+                sandbox.steps.push(Step::Touch(cstring(&target)?));
+            }
+            sandbox.bind(path, &target, true)?;
+        }
+
+        let boxdir = root.join("box");
+        sandbox.mkdir_all(&mut dirs, &boxdir)?;
+        sandbox.bind(&workdir, &boxdir, false)?;
+
+        sandbox.mkdir_all(&mut dirs, &root.join("dev"))?;
+        for device in DEVICES {
+            let target = under(&root, Path::new(device));
+            sandbox.steps.push(Step::Touch(cstring(&target)?));
+            sandbox.bind(Path::new(device), &target, false)?;
+        }
+
+        let proc = root.join("proc");
+        sandbox.mkdir_all(&mut dirs, &proc)?;
+        sandbox.mount(
+            Some("proc"),
+            &proc,
+            Some("proc"),
+            libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+            None,
+        )?;
+
+        let tmp = root.join("tmp");
+        sandbox.mkdir_all(&mut dirs, &tmp)?;
+        sandbox.mount(
+            Some("tmpfs"),
+            &tmp,
+            Some("tmpfs"),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            Some("size=64m,mode=1777"),
+        )?;
+
+        Ok(sandbox)
+    }
+
+    fn mount(
+        &mut self,
+        source: Option<&str>,
+        target: &Path,
+        fstype: Option<&str>,
+        flags: libc::c_ulong,
+        data: Option<&str>,
+    ) -> io::Result<()> {
+        self.steps.push(Step::Mount {
+            source: source.map(CString::new).transpose()?,
+            target: cstring(target)?,
+            fstype: fstype.map(CString::new).transpose()?,
+            flags,
+            data: data.map(CString::new).transpose()?,
+        });
+        Ok(())
+    }
+
+    fn bind(&mut self, source: &Path, target: &Path, readonly: bool) -> io::Result<()> {
+        self.steps.push(Step::Mount {
+            source: Some(cstring(source)?),
+            target: cstring(target)?,
+            fstype: None,
+            flags: libc::MS_BIND | libc::MS_REC,
+            data: None,
+        });
+        if readonly {
+            // Only the top mount is made read-only, submounts keep their flags
+            let flags = libc::MS_BIND
+                | libc::MS_REMOUNT
+                | libc::MS_RDONLY
+                | libc::MS_NOSUID
+                | libc::MS_NODEV
+                | locked_flags(source)?;
+            self.mount(None, target, None, flags, None)?;
+        }
+        Ok(())
+    }
+
+    fn mkdir_all(&mut self, dirs: &mut BTreeSet<PathBuf>, path: &Path) -> io::Result<()> {
+        let mut ancestors: Vec<&Path> = path
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&self.root) && *dir != self.root)
+            .collect();
+        ancestors.reverse();
+        for dir in ancestors {
+            if dirs.insert(dir.to_path_buf()) {
+                self.steps.push(Step::Mkdir(cstring(dir)?));
+            }
+        }
+        Ok(())
+    }
+
+    // Moves the calling process into the sandbox. Runs in the child between
+    // fork and exec.
+    //
+    // A new PID namespace only applies to children, so this forks once more:
+    // the process that eventually execs is pid 1 of the new namespace, and
+    // the one the server spawned just waits for it and passes on how it
+    // exited. Killing the latter's process group takes down both.
+    pub fn enter(&self) -> io::Result<()> {
+        unsafe {
+            check(libc::unshare(
+                libc::CLONE_NEWUSER
+                    | libc::CLONE_NEWNS
+                    | libc::CLONE_NEWPID
+                    | libc::CLONE_NEWNET
+                    | libc::CLONE_NEWIPC
+                    | libc::CLONE_NEWUTS,
+            ))?;
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", self.uid_map.as_bytes())?;
+            write_file(c"/proc/self/gid_map", self.gid_map.as_bytes())?;
+            // Set only now so that RLIMIT_NPROC counts the processes of our
+            // new user namespace rather than every process of the server's
+            // user. Root outside the sandbox is exempt from it altogether.
+            if let Some(limit) = self.process_limit {
+                set_rlimit(libc::RLIMIT_NPROC, limit)?;
+            }
+
+            let pid = libc::fork();
+            if pid < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if pid > 0 {
+                forward_exit(pid);
+            }
+
+            for step in &self.steps {
+                step.apply()?;
+            }
+            check(libc::chdir(self.root_path.as_ptr()))?;
+            check(
+                libc::syscall(libc::SYS_pivot_root, c".".as_ptr(), c".".as_ptr()) as libc::c_int,
+            )?;
+            check(libc::umount2(c".".as_ptr(), libc::MNT_DETACH))?;
+            check(libc::mount(
+                ptr::null(),
+                c"/".as_ptr(),
+                ptr::null(),
+                libc::MS_BIND
+                    | libc::MS_REMOUNT
+                    | libc::MS_RDONLY
+                    | libc::MS_NOSUID
+                    | libc::MS_NODEV,
+                ptr::null(),
+            ))?;
+            check(libc::chdir(c"/box".as_ptr()))?;
+            check(libc::sethostname(c"sandbox".as_ptr(), 7))?;
+
+            drop_capabilities()?;
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+        }
+        seccomp::install(&self.filter)
+    }
+
+    pub fn remove(&self) {
+        // Everything was mounted inside the sandbox's own mount namespace,
+        // so the host only sees an empty directory
+        if let Err(err) = fs::remove_dir(&self.root) {
+            error!(
+                "Unable to remove sandbox root {} :: {}",
+                self.root.display(),
+                err
+            );
+        }
+    }
+}
+
+impl Step {
+    unsafe fn apply(&self) -> io::Result<()> {
+        match self {
+            Step::Mkdir(path) => {
+                if libc::mkdir(path.as_ptr(), 0o755) < 0 {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::AlreadyExists {
+                        return Err(err);
+                    }
+                }
+                Ok(())
+            }
+            Step::Touch(path) => {
+                let fd = libc::open(
+                    path.as_ptr(),
+                    libc::O_WRONLY | libc::O_CREAT | libc::O_CLOEXEC,
+                    0o644,
+                );
+                check(fd)?;
+                libc::close(fd);
+                Ok(())
+            }
+            Step::Symlink { target, path } => check(libc::symlink(target.as_ptr(), path.as_ptr())),
+            Step::Mount {
+                source,
+                target,
+                fstype,
+                flags,
+                data,
+            } => check(libc::mount(
+                source.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+                target.as_ptr(),
+                fstype.as_ref().map_or(ptr::null(), |s| s.as_ptr()),
+                *flags,
+                data.as_ref()
+                    .map_or(ptr::null(), |s| s.as_ptr() as *const libc::c_void),
+            )),
+        }
+    }
+}
+
+unsafe fn write_file(path: &CStr, contents: &[u8]) -> io::Result<()> {
+    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+    check(fd)?;
+    let written = libc::write(fd, contents.as_ptr() as *const libc::c_void, contents.len());
+    libc::close(fd);
+    if written < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+unsafe fn forward_exit(child: libc::pid_t) -> ! {
+    // Closing our copy of std's exec error pipe lets `spawn` return once the
+    // child has exec'd, and closing stdio means the pipes only stay open for
+    // as long as the submission runs.
+    libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0);
+    let mut status = 0;
+    loop {
+        if libc::waitpid(child, &mut status, 0) == child {
+            break;
+        }
+        if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            libc::_exit(127);
+        }
+    }
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+        libc::_exit(128 + signal);
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+unsafe fn drop_capabilities() -> io::Result<()> {
+    // We are root inside the namespace, which would otherwise get every
+    // capability back on exec
+    check(libc::prctl(
+        libc::PR_SET_SECUREBITS,
+        libc::SECBIT_NOROOT
+            | libc::SECBIT_NOROOT_LOCKED
+            | libc::SECBIT_NO_SETUID_FIXUP
+            | libc::SECBIT_NO_SETUID_FIXUP_LOCKED
+            | libc::SECBIT_KEEP_CAPS_LOCKED,
+        0,
+        0,
+        0,
+    ))?;
+    // EINVAL marks the first capability unknown to the running kernel
+    for cap in 0..64 {
+        if libc::prctl(libc::PR_CAPBSET_DROP, cap, 0, 0, 0) < 0 {
+            break;
+        }
+    }
+    check(libc::prctl(
+        libc::PR_CAP_AMBIENT,
+        libc::PR_CAP_AMBIENT_CLEAR_ALL,
+        0,
+        0,
+        0,
+    ))?;
+    let header = CapUserHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0,
+    };
+    let data = [
+        CapUserData {
+            effective: 0,
+            permitted: 0,
+            inheritable: 0,
+        },
+        CapUserData {
+            effective: 0,
+            permitted: 0,
+            inheritable: 0,
+        },
+    ];
+    check(libc::syscall(libc::SYS_capset, &header, data.as_ptr()) as libc::c_int)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn sandboxes_can_share_a_workdir() {
+        let workdir = testing::workdir();
+        let paths = [PathBuf::from("/usr")];
+        let first = Sandbox::new(&workdir, &paths, None).unwrap();
+        let second = Sandbox::new(&workdir, &paths, None).unwrap();
+        assert_ne!(first.root, second.root);
+        first.remove();
+        second.remove();
+        assert!(!first.root.exists() && !second.root.exists());
+        fs::remove_dir(&workdir).unwrap();
+    }
+}
@@ -0,0 +1,453 @@
+use log::error;
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::{
+    checker::{CheckerProgram, Judgement},
+    cleanup, command,
+    config::Config,
+    language::{Language, Sources},
+    runner::{self, Connection, ProcessOutput},
+    types::{CodingError, Verdict},
+    write_files, CompiledProgram,
+};
+
+// Checker programs for problems with several valid answers, and
+// interactors for interactive problems. They follow testlib: run as
+// `checker <input> <output> <answer>` with the files in their folder, they
+// report the verdict through their exit code and explain it on stderr.
+
+const CHECKER_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+// testlib exit codes
+const OK: i32 = 0;
+const WRONG_ANSWER: i32 = 1;
+const PRESENTATION_ERROR: i32 = 2;
+const POINTS: i32 = 7;
+// `_pc(percent)` exits with this plus the percentage
+const PARTIALLY: i32 = 16;
+
+fn checker_error(reason: String) -> CodingError {
+    CodingError::CheckerError { reason }
+}
+
+pub struct SpecialJudge {
+    folder_name: String,
+    sources: Sources,
+    language: Arc<dyn Language>,
+}
+
+impl SpecialJudge {
+    async fn compile(
+        program: &CheckerProgram,
+        config: &Config,
+    ) -> Result<SpecialJudge, CodingError> {
+        let language = config
+            .languages
+            .get(&program.language, program.language_version.as_deref())
+            .filter(|language| config.languages.is_available(language.as_ref()))
+            .ok_or_else(|| {
+                checker_error(format!(
+                    "`{}` is not an available language",
+                    program.language
+                ))
+            })?;
+        let file_name = language.source_file_name(&program.source_code)?;
+        let folder_name = format!("checkers/{}{}", language.name(), Uuid::new_v4());
+        let files = vec![(file_name.clone(), program.source_code.clone().into_bytes())];
+        write_files(&folder_name, files).await?;
+        let judge = SpecialJudge {
+            folder_name,
+            sources: Sources {
+                entry_point: file_name.clone(),
+                files: vec![file_name],
+            },
+            language,
+        };
+        if judge.language.is_compiled() {
+            let compiled = CompiledProgram {
+                folder_name: judge.folder_name.clone(),
+                sources: judge.sources.clone(),
+                language: judge.language.clone(),
+            };
+            // Dropping the judge removes its folder
+            if let Err(err) = compiled.compile(config).await {
+                return Err(match err {
+                    CodingError::CompileError { stderr, .. } => {
+                        checker_error(format!("The checker failed to compile :: {}", stderr))
+                    }
+                    err => err,
+                });
+            }
+        }
+        Ok(judge)
+    }
+
+    // Writes a testcase's files, in a folder of their own as the judge is
+    // shared, and returns that folder and the command to run
+    async fn prepare(
+        &self,
+        input: &str,
+        output: &str,
+        answer: &str,
+    ) -> Result<(String, Vec<String>), CodingError> {
+        let run_folder = Uuid::new_v4().to_string();
+        let files = vec![
+            (String::from("input.txt"), input.as_bytes().to_vec()),
+            (String::from("output.txt"), output.as_bytes().to_vec()),
+            (String::from("answer.txt"), answer.as_bytes().to_vec()),
+        ];
+        write_files(&format!("{}/{}", self.folder_name, run_folder), files).await?;
+        let mut argv = self.language.run_command(&self.sources)?;
+        for file in ["input.txt", "output.txt", "answer.txt"] {
+            argv.push(format!("{}/{}", run_folder, file));
+        }
+        Ok((run_folder, argv))
+    }
+
+    pub async fn judge(
+        &self,
+        input: &str,
+        output: &str,
+        answer: &str,
+        config: &Config,
+    ) -> Result<Judgement, CodingError> {
+        let (run_folder, argv) = self.prepare(input, output, answer).await?;
+        let workdir = format!("tmp/{}", self.folder_name);
+        let limits = config.limits(CHECKER_TIME_LIMIT, None);
+        let result = runner::run(command(&argv)?, Path::new(&workdir), "", &limits, config).await;
+        cleanup(&format!("{}/{}", workdir, run_folder)).await?;
+        judgement(result?, "checker")
+    }
+
+    // Runs as the interactor of an interactive problem, talking to the
+    // submission through `connection`. Its output file starts out empty,
+    // for it to log to. It outlives the submission, so it gets more time.
+    pub async fn interact(
+        &self,
+        input: &str,
+        answer: &str,
+        connection: Connection,
+        time: Duration,
+        config: &Config,
+    ) -> Result<Judgement, CodingError> {
+        let (run_folder, argv) = self.prepare(input, "", answer).await?;
+        let workdir = format!("tmp/{}", self.folder_name);
+        let limits = config.limits(time + CHECKER_TIME_LIMIT, None);
+        let result = runner::run_connected(
+            command(&argv)?,
+            Path::new(&workdir),
+            connection,
+            &limits,
+            config,
+        )
+        .await;
+        cleanup(&format!("{}/{}", workdir, run_folder)).await?;
+        judgement(result?, "interactor")
+    }
+}
+
+// The verdict a checker or interactor gave through its exit code
+fn judgement(output: ProcessOutput, role: &str) -> Result<Judgement, CodingError> {
+    if output.timed_out {
+        return Err(checker_error(format!("The {} timed out", role)));
+    }
+    let message = output.stderr.trim();
+    let note = (!message.is_empty()).then(|| message.to_string());
+    let verdict = |verdict: Verdict| Judgement {
+        message: note.clone(),
+        ..Judgement::from(verdict)
+    };
+    let partial = |score: f64| Judgement {
+        verdict: if score >= 1.0 {
+            Verdict::Accepted
+        } else if score <= 0.0 {
+            Verdict::WrongAnswer
+        } else {
+            Verdict::PartiallyAccepted
+        },
+        score: score.clamp(0.0, 1.0),
+        message: note.clone(),
+        diff: None,
+    };
+    match output.exit_code {
+        Some(OK) => Ok(verdict(Verdict::Accepted)),
+        Some(WRONG_ANSWER | PRESENTATION_ERROR) => Ok(verdict(Verdict::WrongAnswer)),
+        // testlib's `quitp` starts the message with the score
+        Some(POINTS) => {
+            let score = message
+                .trim_start_matches("points ")
+                .split_whitespace()
+                .next()
+                .and_then(|score| score.parse::<f64>().ok());
+            match score {
+                Some(score) if score.is_finite() => Ok(partial(score)),
+                _ => Err(checker_error(format!(
+                    "The {} gave points without a score :: {}",
+                    role, message
+                ))),
+            }
+        }
+        Some(code) if (PARTIALLY..=PARTIALLY + 100).contains(&code) => {
+            Ok(partial(f64::from(code - PARTIALLY) / 100.0))
+        }
+        Some(code) => Err(checker_error(format!(
+            "The {} failed with exit code {} :: {}",
+            role, code, message
+        ))),
+        None => Err(checker_error(format!(
+            "The {} was killed by a signal :: {}",
+            role, message
+        ))),
+    }
+}
+
+// The folder goes once the judge is out of the cache and done with
+impl Drop for SpecialJudge {
+    fn drop(&mut self) {
+        let folder = format!("tmp/{}", self.folder_name);
+        if let Err(err) = std::fs::remove_dir_all(&folder) {
+            error!("Unable to remove {} :: {}", folder, err);
+        }
+    }
+}
+
+type Cell = Arc<OnceCell<Arc<SpecialJudge>>>;
+
+// Checker programs compiled so far, so each one is only compiled once. Any
+// client can send checkers, so only the most recently used are kept.
+pub struct SpecialJudges {
+    capacity: usize,
+    compiled: Mutex<Compiled>,
+}
+
+#[derive(Default)]
+struct Compiled {
+    // Each with when it was last asked for
+    cells: HashMap<CheckerProgram, (Cell, u64)>,
+    clock: u64,
+}
+
+impl SpecialJudges {
+    pub fn new(capacity: usize) -> SpecialJudges {
+        SpecialJudges {
+            capacity,
+            compiled: Mutex::new(Compiled::default()),
+        }
+    }
+
+    fn cell(&self, program: &CheckerProgram) -> Result<Cell, CodingError> {
+        let mut compiled = match self.compiled.lock() {
+            Ok(compiled) => compiled,
+            Err(err) => {
+                error!("Checker cache poisoned :: {}", err);
+                return Err(checker_error(String::from("The checker cache is unusable")));
+            }
+        };
+        compiled.clock += 1;
+        let now = compiled.clock;
+        if let Some((cell, last_used)) = compiled.cells.get_mut(program) {
+            *last_used = now;
+            return Ok(cell.clone());
+        }
+        if compiled.cells.len() >= self.capacity {
+            let oldest = compiled
+                .cells
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(program, _)| program.clone());
+            if let Some(oldest) = oldest {
+                compiled.cells.remove(&oldest);
+            }
+        }
+        let cell = Cell::default();
+        compiled.cells.insert(program.clone(), (cell.clone(), now));
+        Ok(cell)
+    }
+
+    pub async fn get(
+        &self,
+        program: &CheckerProgram,
+        config: &Config,
+    ) -> Result<Arc<SpecialJudge>, CodingError> {
+        let cell = self.cell(program)?;
+        // Failed compilations leave the cell empty and are tried again
+        cell.get_or_try_init(|| async {
+            SpecialJudge::compile(program, config).await.map(Arc::new)
+        })
+        .await
+        .cloned()
+    }
+}
+
+impl fmt::Debug for SpecialJudges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpecialJudges").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::SandboxKind, testing};
+    use std::process::Command;
+
+    fn output(exit_code: Option<i32>, stderr: &str) -> ProcessOutput {
+        ProcessOutput {
+            stderr: stderr.to_string(),
+            exit_code,
+            ..ProcessOutput::default()
+        }
+    }
+
+    fn judge(exit_code: Option<i32>, stderr: &str) -> Judgement {
+        judgement(output(exit_code, stderr), "checker").unwrap()
+    }
+
+    fn reason(exit_code: Option<i32>, stderr: &str) -> String {
+        testing::reason(judgement(output(exit_code, stderr), "checker"))
+    }
+
+    fn program(source_code: &str) -> CheckerProgram {
+        CheckerProgram {
+            language: "cpp".to_string(),
+            language_version: None,
+            source_code: source_code.to_string(),
+        }
+    }
+
+    #[test]
+    fn accepted() {
+        let judgement = judge(Some(OK), "ok 3 numbers\n");
+        assert_eq!(judgement.verdict, Verdict::Accepted);
+        assert_eq!(judgement.score, 1.0);
+        assert_eq!(judgement.message.as_deref(), Some("ok 3 numbers"));
+        assert!(judge(Some(OK), "  \n").message.is_none());
+    }
+
+    #[test]
+    fn wrong_answer_and_presentation_error() {
+        for code in [WRONG_ANSWER, PRESENTATION_ERROR] {
+            let judgement = judge(Some(code), "wrong answer");
+            assert_eq!(judgement.verdict, Verdict::WrongAnswer);
+            assert_eq!(judgement.score, 0.0);
+        }
+    }
+
+    #[test]
+    fn points() {
+        let judgement = judge(Some(POINTS), "points 0.25 almost there");
+        assert_eq!(judgement.verdict, Verdict::PartiallyAccepted);
+        assert_eq!(judgement.score, 0.25);
+        assert_eq!(judge(Some(POINTS), "1.5").verdict, Verdict::Accepted);
+        assert_eq!(judge(Some(POINTS), "1.5").score, 1.0);
+        assert_eq!(judge(Some(POINTS), "-2").verdict, Verdict::WrongAnswer);
+        assert_eq!(judge(Some(POINTS), "-2").score, 0.0);
+        assert_eq!(
+            reason(Some(POINTS), "points nan"),
+            "The checker gave points without a score :: points nan"
+        );
+        assert!(reason(Some(POINTS), "").starts_with("The checker gave points"));
+    }
+
+    #[test]
+    fn partially() {
+        let judgement = judge(Some(PARTIALLY + 40), "");
+        assert_eq!(judgement.verdict, Verdict::PartiallyAccepted);
+        assert_eq!(judgement.score, 0.4);
+        assert_eq!(judge(Some(PARTIALLY), "").verdict, Verdict::WrongAnswer);
+        assert_eq!(judge(Some(PARTIALLY + 100), "").verdict, Verdict::Accepted);
+        assert_eq!(
+            reason(Some(PARTIALLY + 101), "oops"),
+            "The checker failed with exit code 117 :: oops"
+        );
+    }
+
+    #[test]
+    fn failures() {
+        assert_eq!(
+            reason(Some(3), "fail"),
+            "The checker failed with exit code 3 :: fail"
+        );
+        assert_eq!(
+            reason(None, "aborted"),
+            "The checker was killed by a signal :: aborted"
+        );
+        let timed_out = ProcessOutput {
+            timed_out: true,
+            ..output(Some(OK), "")
+        };
+        assert_eq!(
+            testing::reason(judgement(timed_out, "interactor")),
+            "The interactor timed out"
+        );
+    }
+
+    // Sends the number in the input, expects the answer back
+    const INTERACTOR: &str = "import sys
+print(open(sys.argv[1]).read().strip(), flush=True)
+reply = sys.stdin.readline().strip()
+sys.exit(0 if reply == open(sys.argv[3]).read().strip() else 1)
+";
+
+    #[actix_web::test]
+    async fn cached_interactor_runs_side_by_side() {
+        let config = Config {
+            sandbox: SandboxKind::Namespaces,
+            special_judges: Arc::new(SpecialJudges::new(1)),
+            ..testing::config()
+        };
+        let program = CheckerProgram {
+            language: "python".to_string(),
+            language_version: None,
+            source_code: INTERACTOR.to_string(),
+        };
+        let interactor = config.special_judges.get(&program, &config).await.unwrap();
+        let workdir = testing::workdir();
+        let limits = config.limits(Duration::from_secs(5), None);
+        let (config, interactor, workdir, limits) = (&config, &interactor, &workdir, &limits);
+        let runs = (0..4).map(|n| async move {
+            let (input, answer) = (n.to_string(), (n + 1).to_string());
+            let (program_end, interactor_end) = runner::connect().unwrap();
+            let mut submission = Command::new("sh");
+            submission.args(["-c", "read n; echo $((n + 1))"]);
+            let (output, judgement) = tokio::join!(
+                runner::run_connected(submission, workdir, program_end, limits, config),
+                interactor.interact(&input, &answer, interactor_end, limits.time, config),
+            );
+            (
+                output.map(|output| output.exit_code),
+                judgement.map(|judgement| judgement.verdict),
+            )
+        });
+        let results = futures_util::future::join_all(runs).await;
+        std::fs::remove_dir(workdir).unwrap();
+        for (exit_code, verdict) in results {
+            assert_eq!(exit_code.unwrap(), Some(0));
+            assert_eq!(verdict.unwrap(), Verdict::Accepted);
+        }
+    }
+
+    #[test]
+    fn cache_evicts_the_least_recently_used() {
+        let judges = SpecialJudges::new(2);
+        let (a, b, c) = (program("a"), program("b"), program("c"));
+        let first = judges.cell(&a).unwrap();
+        judges.cell(&b).unwrap();
+        // `a` is now more recent than `b`
+        assert!(Arc::ptr_eq(&first, &judges.cell(&a).unwrap()));
+        judges.cell(&c).unwrap();
+        let compiled = judges.compiled.lock().unwrap();
+        assert_eq!(compiled.cells.len(), 2);
+        assert!(compiled.cells.contains_key(&a));
+        assert!(!compiled.cells.contains_key(&b));
+        assert!(compiled.cells.contains_key(&c));
+    }
+}
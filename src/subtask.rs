@@ -0,0 +1,225 @@
+use serde::Deserialize;
+
+use crate::types::{CodingError, SubtaskResult, TestcaseResult, Verdict};
+
+// Olympiad style scoring: testcases are grouped into subtasks worth some
+// points each, e.g. `"subtasks": [{"points": 30, "testcases": [0, 1]},
+// {"points": 70, "testcases": [2, 3, 4], "scoring": "sum"}]`. A testcase
+// can be part of several subtasks, or of none, like the examples.
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    // The points if every testcase is accepted, none otherwise
+    #[default]
+    AllOrNothing,
+    // The points shared evenly between the testcases
+    Sum,
+    // The points times the lowest testcase score
+    Min,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Subtask {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub points: f64,
+    // Indices into `sample_testcases`
+    pub testcases: Vec<usize>,
+    #[serde(default)]
+    pub scoring: Scoring,
+}
+
+fn invalid(index: usize, reason: impl ToString) -> CodingError {
+    CodingError::InvalidSubmission {
+        reason: format!("Subtask {} {}", index, reason.to_string()),
+    }
+}
+
+pub fn validate(subtasks: &[Subtask], testcase_count: usize) -> Result<(), CodingError> {
+    for (index, subtask) in subtasks.iter().enumerate() {
+        if !subtask.points.is_finite() || subtask.points < 0.0 {
+            return Err(invalid(index, "can't be worth less than zero points"));
+        }
+        if subtask.testcases.is_empty() {
+            return Err(invalid(index, "has no testcases"));
+        }
+        if let Some(testcase) = subtask.testcases.iter().find(|&&t| t >= testcase_count) {
+            return Err(invalid(
+                index,
+                format!("refers to testcase {}, which doesn't exist", testcase),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Expects every testcase to have been run, as `validate` made sure they exist
+pub fn score(subtasks: &[Subtask], testcases: &[TestcaseResult]) -> Vec<SubtaskResult> {
+    subtasks
+        .iter()
+        .enumerate()
+        .map(|(index, subtask)| {
+            let results: Vec<&TestcaseResult> =
+                subtask.testcases.iter().map(|&t| &testcases[t]).collect();
+            let verdict = results
+                .iter()
+                .map(|result| result.verdict)
+                .find(|verdict| *verdict != Verdict::Accepted)
+                .unwrap_or(Verdict::Accepted);
+            let fraction = match subtask.scoring {
+                Scoring::AllOrNothing => {
+                    if verdict == Verdict::Accepted {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                Scoring::Sum => {
+                    results.iter().map(|result| result.score).sum::<f64>() / results.len() as f64
+                }
+                Scoring::Min => results
+                    .iter()
+                    .map(|result| result.score)
+                    .fold(1.0, f64::min),
+            };
+            SubtaskResult {
+                index,
+                name: subtask.name.clone(),
+                verdict,
+                score: subtask.points * fraction,
+                points: subtask.points,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::reason;
+
+    fn subtask(points: f64, testcases: &[usize], scoring: Scoring) -> Subtask {
+        Subtask {
+            name: None,
+            points,
+            testcases: testcases.to_vec(),
+            scoring,
+        }
+    }
+
+    fn result(index: usize, verdict: Verdict, score: f64) -> TestcaseResult {
+        TestcaseResult {
+            index,
+            verdict,
+            score,
+            ..TestcaseResult::default()
+        }
+    }
+
+    fn results() -> Vec<TestcaseResult> {
+        vec![
+            result(0, Verdict::Accepted, 1.0),
+            result(1, Verdict::Accepted, 1.0),
+            result(2, Verdict::PartiallyAccepted, 0.5),
+            result(3, Verdict::WrongAnswer, 0.0),
+        ]
+    }
+
+    #[test]
+    fn all_or_nothing() {
+        let subtasks = [
+            subtask(30.0, &[0, 1], Scoring::AllOrNothing),
+            subtask(70.0, &[1, 2], Scoring::AllOrNothing),
+        ];
+        let scores = score(&subtasks, &results());
+        assert_eq!(scores[0].verdict, Verdict::Accepted);
+        assert_eq!(scores[0].score, 30.0);
+        assert_eq!(scores[1].verdict, Verdict::PartiallyAccepted);
+        assert_eq!(scores[1].score, 0.0);
+        assert_eq!(scores[1].points, 70.0);
+    }
+
+    #[test]
+    fn sum_shares_the_points() {
+        let subtasks = [subtask(40.0, &[0, 1, 2, 3], Scoring::Sum)];
+        let scores = score(&subtasks, &results());
+        assert_eq!(scores[0].score, 25.0);
+        // The first testcase that wasn't accepted
+        assert_eq!(scores[0].verdict, Verdict::PartiallyAccepted);
+    }
+
+    #[test]
+    fn min_takes_the_lowest_score() {
+        let subtasks = [
+            subtask(10.0, &[0, 2], Scoring::Min),
+            subtask(10.0, &[3, 0], Scoring::Min),
+        ];
+        let scores = score(&subtasks, &results());
+        assert_eq!(scores[0].score, 5.0);
+        assert_eq!(scores[1].score, 0.0);
+        assert_eq!(scores[1].verdict, Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn results_keep_the_subtask_order_and_names() {
+        let mut named = subtask(1.0, &[0], Scoring::AllOrNothing);
+        named.name = Some("small".to_string());
+        let subtasks = [subtask(1.0, &[3], Scoring::AllOrNothing), named];
+        let scores = score(&subtasks, &results());
+        assert_eq!(scores[0].index, 0);
+        assert_eq!(scores[0].name, None);
+        assert_eq!(scores[1].index, 1);
+        assert_eq!(scores[1].name.as_deref(), Some("small"));
+    }
+
+    #[test]
+    fn scoring_is_picked_from_json() {
+        let subtask: Subtask =
+            serde_json::from_str(r#"{"points": 5, "testcases": [0], "scoring": "min"}"#).unwrap();
+        assert!(matches!(subtask.scoring, Scoring::Min));
+        let subtask: Subtask = serde_json::from_str(r#"{"points": 5, "testcases": [0]}"#).unwrap();
+        assert!(matches!(subtask.scoring, Scoring::AllOrNothing));
+        assert!(serde_json::from_str::<Subtask>(r#"{"points": 5, "tests": [0]}"#).is_err());
+    }
+
+    #[test]
+    fn validate_accepts_shared_and_unused_testcases() {
+        let subtasks = [
+            subtask(0.0, &[1, 2], Scoring::Sum),
+            subtask(50.0, &[2, 3], Scoring::AllOrNothing),
+        ];
+        assert!(validate(&subtasks, 4).is_ok());
+        assert!(validate(&[], 0).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_bad_points() {
+        for points in [-1.0, f64::NAN, f64::INFINITY] {
+            let subtasks = [subtask(points, &[0], Scoring::Sum)];
+            assert_eq!(
+                reason(validate(&subtasks, 1)),
+                "Subtask 0 can't be worth less than zero points"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_rejects_empty_subtasks() {
+        let subtasks = [
+            subtask(1.0, &[0], Scoring::Sum),
+            subtask(1.0, &[], Scoring::Sum),
+        ];
+        assert_eq!(reason(validate(&subtasks, 1)), "Subtask 1 has no testcases");
+    }
+
+    #[test]
+    fn validate_rejects_missing_testcases() {
+        let subtasks = [subtask(1.0, &[0, 2], Scoring::Sum)];
+        assert_eq!(
+            reason(validate(&subtasks, 2)),
+            "Subtask 0 refers to testcase 2, which doesn't exist"
+        );
+    }
+}
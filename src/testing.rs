@@ -0,0 +1,40 @@
+use std::{path::PathBuf, sync::OnceLock};
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, SandboxKind},
+    types::CodingError,
+};
+
+// Helpers shared by the unit tests
+
+// Why a submission or a checker was rejected, for tests expecting exactly that
+pub fn reason<T>(result: Result<T, CodingError>) -> String {
+    match result {
+        Err(CodingError::InvalidSubmission { reason } | CodingError::CheckerError { reason }) => {
+            reason
+        }
+        Err(err) => panic!("unexpected error {}", err),
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+// The server's settings from the environment, without a sandbox or a
+// cgroup. Toolchains are only detected once.
+pub fn config() -> Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| Config {
+            sandbox: SandboxKind::None,
+            cgroup_root: None,
+            ..Config::from_env().unwrap()
+        })
+        .clone()
+}
+
+// An empty folder for a test to run programs in
+pub fn workdir() -> PathBuf {
+    let workdir = std::env::temp_dir().join(format!("rustycoding-{}", Uuid::new_v4()));
+    std::fs::create_dir(&workdir).unwrap();
+    workdir
+}
@@ -1,13 +1,89 @@
+use crate::{
+    checker::{Checker, CheckerProgram},
+    harness::Harness,
+    subtask::Subtask,
+};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
+use std::time::Duration;
 
 #[derive(Deserialize)]
 pub struct Question {
     pub id: String,
     pub language: String,
+    // Picks one of the versions configured for the language
+    #[serde(default)]
+    pub language_version: Option<String>,
+    #[serde(default)]
     pub source_code: String,
+    // Submissions made of several files give them here instead of
+    // `source_code`, along with the one to compile or run
+    #[serde(default)]
+    pub files: Vec<SourceFile>,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    // zip or tar.gz of the files, uploaded next to the question
+    #[serde(skip)]
+    pub archive: Option<Vec<u8>>,
+    // For questions where `source_code` is only a function, see `harness`
+    #[serde(default)]
+    pub harness: Option<Harness>,
+    // Compares outputs with the expected ones, whitespace around them is
+    // ignored by default
+    #[serde(default)]
+    pub checker: Checker,
+    // Makes the question interactive: the submission talks to this program
+    // rather than reading the input, and it decides the verdicts
+    #[serde(default)]
+    pub interactor: Option<CheckerProgram>,
     pub timeout: i32,
+    #[serde(default)]
+    pub memory_limit_mb: Option<u64>,
+    #[serde(default)]
     pub sample_testcases: Vec<(String, String)>,
+    // Runs the code once on this input instead of the testcases, returning
+    // what it printed without checking it
+    #[serde(default)]
+    pub stdin: Option<String>,
+    // Groups the testcases into subtasks worth points, see `subtask`
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+}
+
+// A submission to one of the problems stored on the server, see `problem`
+#[derive(Deserialize)]
+pub struct Submission {
+    pub id: String,
+    pub problem: String,
+    pub language: String,
+    #[serde(default)]
+    pub language_version: Option<String>,
+    #[serde(default)]
+    pub source_code: String,
+    #[serde(default)]
+    pub files: Vec<SourceFile>,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    #[serde(skip)]
+    pub archive: Option<Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+pub struct SourceFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Clone)]
+pub struct Limits {
+    pub time: Duration,
+    pub memory_bytes: Option<u64>,
+    // Processes and threads alike
+    pub processes: Option<u64>,
+    pub open_files: Option<u64>,
+    pub file_size_bytes: Option<u64>,
+    // Per stream
+    pub output_bytes: Option<u64>,
 }
 
 #[derive(Debug, Snafu)]
@@ -27,15 +103,278 @@ pub enum CodingError {
     ))]
     InvalidStringFromConsole,
     #[snafu(display("CompileError :: An error occurred during compilation of source code."))]
-    CompileError,
+    CompileError {
+        stderr: String,
+        diagnostics: Vec<Diagnostic>,
+    },
     #[snafu(display("CleanupError :: An error occurred during cleanup of source code."))]
     CleanupError,
     #[snafu(display("TimeLimitExceeded :: The user's program exceeded the time limit."))]
     TimeLimitExceeded,
+    #[snafu(display("InvalidConfig :: {}", reason))]
+    InvalidConfig { reason: String },
+    #[snafu(display("UnsupportedLanguage :: `{}` is not a supported language.", language))]
+    UnsupportedLanguage { language: String },
+    #[snafu(display(
+        "LanguageUnavailable :: The toolchain for `{}` is not installed on this server.",
+        language
+    ))]
+    LanguageUnavailable { language: String },
+    #[snafu(display("InvalidSubmission :: {}", reason))]
+    InvalidSubmission { reason: String },
+    #[snafu(display("CheckerError :: {}", reason))]
+    CheckerError { reason: String },
+    #[snafu(display("UnknownProblem :: There is no problem named `{}`.", problem))]
+    UnknownProblem { problem: String },
+}
+
+impl CodingError {
+    pub fn verdict(&self) -> Verdict {
+        match self {
+            CodingError::InvalidPublicClass => Verdict::InvalidFile,
+            CodingError::FileNameError => Verdict::InvalidFile,
+            CodingError::UnsupportedLanguage { .. } => Verdict::InvalidFile,
+            CodingError::LanguageUnavailable { .. } => Verdict::InvalidFile,
+            CodingError::InvalidSubmission { .. } => Verdict::InvalidFile,
+            CodingError::CompileError { .. } => Verdict::CompilationError,
+            CodingError::TimeLimitExceeded => Verdict::TimeLimitExceeded,
+            CodingError::UnknownProblem { .. } => Verdict::FileNotFound,
+            _ => Verdict::InternalServerError,
+        }
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Verdict {
+    Ok = 200,
+    Accepted = 201,
+    PartiallyAccepted = 206,
+    WrongAnswer = 400,
+    CompilationError = 401,
+    RuntimeError = 402,
+    InvalidFile = 403,
+    FileNotFound = 404,
+    TimeLimitExceeded = 408,
+    MemoryLimitExceeded = 409,
+    ResourceLimitExceeded = 410,
+    OutputLimitExceeded = 411,
+    InternalServerError = 500,
+}
+
+impl Verdict {
+    pub fn code(&self) -> i32 {
+        *self as i32
+    }
+
+    pub fn message(&self) -> &str {
+        match *self {
+            Verdict::Ok => "Success",
+            Verdict::Accepted => "Accepted",
+            Verdict::PartiallyAccepted => "Partially Accepted",
+            Verdict::WrongAnswer => "Wrong Answer",
+            Verdict::CompilationError => "Compilation Error",
+            Verdict::RuntimeError => "Runtime Error",
+            Verdict::InvalidFile => "Invalid File",
+            Verdict::FileNotFound => "File Not Found",
+            Verdict::TimeLimitExceeded => "Time Limit Exceeded",
+            Verdict::MemoryLimitExceeded => "Memory Limit Exceeded",
+            Verdict::ResourceLimitExceeded => "Resource Limit Exceeded",
+            Verdict::OutputLimitExceeded => "Output Limit Exceeded",
+            Verdict::InternalServerError => "Internal Server Error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
 }
 
 #[derive(Serialize)]
-pub struct ErrorResponse {
+pub struct LanguageInfo {
+    pub name: String,
+    pub language_version: Option<String>,
+    pub aliases: Vec<String>,
+    pub compiled: bool,
+    // Whether the toolchain ran in the sandbox when the server started
+    pub available: bool,
+    pub version: Option<String>,
+    // Whether questions with a harness can be answered in it
+    pub harness: Option<String>,
+    pub compile_command: Option<String>,
+    pub run_command: String,
+}
+
+#[derive(Serialize)]
+pub struct SubtaskResult {
+    pub index: usize,
+    pub name: Option<String>,
+    // The first verdict other than `Accepted` among its testcases
+    pub verdict: Verdict,
+    pub score: f64,
+    // The most it could have scored
+    pub points: f64,
+}
+
+// Where a wrong answer departs from the expected output
+#[derive(Serialize)]
+pub struct OutputDiff {
+    // 1-based, of the first line that differs
+    pub line: usize,
+    pub column: usize,
+    // That line on each side, missing past the end of the output
+    pub expected_line: Option<String>,
+    pub actual_line: Option<String>,
+    pub unified: String,
+    // Set when `unified` was cut off at the diff limit
+    pub truncated: bool,
+}
+
+#[derive(Serialize)]
+pub struct TestcaseResult {
+    pub index: usize,
+    pub verdict: Verdict,
+    pub stdout: String,
+    pub stderr: String,
+    // Set when the stream was cut off at the output limit
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub exit_code: Option<i32>,
+    // 1 for accepted testcases, 0 for failed ones and in between for those
+    // a checker program partially accepted
+    pub score: f64,
+    pub checker_message: Option<String>,
+    pub diff: Option<OutputDiff>,
+    pub time_ms: u64,
+    pub cpu_time_ms: u64,
+    pub peak_memory_kb: u64,
+}
+
+// A testcase that couldn't be run
+impl Default for TestcaseResult {
+    fn default() -> TestcaseResult {
+        TestcaseResult {
+            index: 0,
+            verdict: Verdict::InternalServerError,
+            stdout: String::new(),
+            stderr: String::new(),
+            stdout_truncated: false,
+            stderr_truncated: false,
+            exit_code: None,
+            score: 0.0,
+            checker_message: None,
+            diff: None,
+            time_ms: 0,
+            cpu_time_ms: 0,
+            peak_memory_kb: 0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RunResult {
+    pub id: String,
+    pub verdict: Verdict,
+    pub status: i32,
+    pub message: String,
+    // Points earned over the subtasks, or the sum of the testcase scores
+    // when there are none
+    pub score: f64,
+    pub error: Option<String>,
+    pub compile_output: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub subtasks: Vec<SubtaskResult>,
+    pub testcases: Vec<TestcaseResult>,
+}
+
+impl RunResult {
+    pub fn from_error(id: String, err: CodingError) -> RunResult {
+        let verdict = err.verdict();
+        let error = Some(err.to_string());
+        let (compile_output, diagnostics) = match err {
+            CodingError::CompileError {
+                stderr,
+                diagnostics,
+            } => (Some(stderr), diagnostics),
+            _ => (None, Vec::new()),
+        };
+        RunResult {
+            id,
+            verdict,
+            status: verdict.code(),
+            message: verdict.message().to_string(),
+            score: 0.0,
+            error,
+            compile_output,
+            diagnostics,
+            subtasks: Vec::new(),
+            testcases: Vec::new(),
+        }
+    }
+}
+
+// What `/submit` returns: outputs could give the hidden testcases away, so
+// only verdicts, scores and resource usage are kept
+#[derive(Serialize)]
+pub struct SubmitResult {
     pub id: String,
-    pub error: String,
+    pub verdict: Verdict,
+    pub status: i32,
+    pub message: String,
+    pub score: f64,
+    pub error: Option<String>,
+    pub compile_output: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub subtasks: Vec<SubtaskResult>,
+    pub testcases: Vec<TestcaseVerdict>,
+}
+
+#[derive(Serialize)]
+pub struct TestcaseVerdict {
+    pub index: usize,
+    pub verdict: Verdict,
+    pub score: f64,
+    pub time_ms: u64,
+    pub cpu_time_ms: u64,
+    pub peak_memory_kb: u64,
+}
+
+impl From<RunResult> for SubmitResult {
+    fn from(result: RunResult) -> SubmitResult {
+        SubmitResult {
+            id: result.id,
+            verdict: result.verdict,
+            status: result.status,
+            message: result.message,
+            score: result.score,
+            error: result.error,
+            compile_output: result.compile_output,
+            diagnostics: result.diagnostics,
+            subtasks: result.subtasks,
+            testcases: result
+                .testcases
+                .into_iter()
+                .map(|testcase| TestcaseVerdict {
+                    index: testcase.index,
+                    verdict: testcase.verdict,
+                    score: testcase.score,
+                    time_ms: testcase.time_ms,
+                    cpu_time_ms: testcase.cpu_time_ms,
+                    peak_memory_kb: testcase.peak_memory_kb,
+                })
+                .collect(),
+        }
+    }
 }